@@ -0,0 +1,31 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::hint::black_box;
+use wg_restarter::wg;
+
+/// Fake `wg show latest-handshakes` output with `peers` peers.
+fn latest_handshakes(peers: usize) -> Vec<u8> {
+    (0..peers)
+        .map(|i| format!("{i:0>43}=\t{}\n", 1_700_000_000 + i))
+        .collect::<String>()
+        .into_bytes()
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let one = latest_handshakes(1);
+    let many = latest_handshakes(100);
+
+    c.bench_function("first_peer_handshake_ts/1 peer", |b| {
+        b.iter(|| wg::first_peer_handshake_ts(black_box(&one)))
+    });
+
+    c.bench_function("first_peer_handshake_ts/100 peers", |b| {
+        b.iter(|| wg::first_peer_handshake_ts(black_box(&many)))
+    });
+
+    c.bench_function("peer_handshakes/100 peers", |b| {
+        b.iter(|| wg::peer_handshakes(black_box(&many)).map(|peers| peers.len()))
+    });
+}
+
+criterion_group!(benches, bench_parse);
+criterion_main!(benches);
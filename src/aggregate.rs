@@ -0,0 +1,123 @@
+//! `--aggregate-window`, which holds back restart notifications when
+//! monitoring several interfaces, so that an outage taking down all tunnels
+//! at once is announced with one notification rather than one per interface.
+
+use crate::notify::Event;
+use std::sync::{self, atomic};
+use std::{thread, time};
+
+/// Outcomes that are held back and sent together; all others are sent at once.
+pub const AGGREGATED: &[&str] = &["restarted", "restart failed", "restart unverified"];
+
+/// How often waiting is interrupted to see whether monitoring is ending.
+const POLL_INTERVAL: time::Duration = time::Duration::from_secs(1);
+
+/// An event held back until the window closes.
+struct Pending {
+    interface: String,
+    unit: String,
+    elapsed: Option<time::Duration>,
+    timeout: time::Duration,
+    outcome: String,
+}
+
+/// Events from all interfaces, waiting for the window after the first of them to close.
+pub struct Aggregator {
+    window: time::Duration,
+    pending: sync::Mutex<Vec<Pending>>,
+    arrived: sync::Condvar,
+}
+
+impl Aggregator {
+    pub fn new(window: time::Duration) -> Self {
+        Self {
+            window,
+            pending: sync::Mutex::new(Vec::new()),
+            arrived: sync::Condvar::new(),
+        }
+    }
+
+    /// Hold back `event` until the window closes.
+    pub fn push(&self, event: &Event) {
+        self.lock().push(Pending {
+            interface: event.interface.to_string(),
+            unit: event.unit.to_string(),
+            elapsed: event.elapsed,
+            timeout: event.timeout,
+            outcome: event.outcome.to_string(),
+        });
+
+        self.arrived.notify_one();
+    }
+
+    fn lock(&self) -> sync::MutexGuard<'_, Vec<Pending>> {
+        self.pending.lock().unwrap_or_else(sync::PoisonError::into_inner)
+    }
+
+    /// Pass held back events to `dispatch` as the window after the first of each batch closes, until `stop` is set.
+    ///
+    /// Whatever is still held back when `stop` is set is dispatched right away.
+    pub fn run(&self, stop: &atomic::AtomicBool, dispatch: impl Fn(&Event)) {
+        loop {
+            let mut pending = self.lock();
+
+            while pending.is_empty() {
+                if stop.load(atomic::Ordering::Relaxed) {
+                    return;
+                }
+
+                pending = self.arrived.wait_timeout(pending, POLL_INTERVAL)
+                    .unwrap_or_else(sync::PoisonError::into_inner)
+                    .0;
+            }
+
+            drop(pending);
+            let opened = time::Instant::now();
+
+            while !stop.load(atomic::Ordering::Relaxed) && opened.elapsed() < self.window {
+                thread::sleep(POLL_INTERVAL.min(self.window.saturating_sub(opened.elapsed())));
+            }
+
+            let batch = std::mem::take(&mut *self.lock());
+            dispatch_batch(&batch, &dispatch);
+        }
+    }
+}
+
+/// Dispatch a batch as one event, listing every interface in it.
+fn dispatch_batch(batch: &[Pending], dispatch: &impl Fn(&Event)) {
+    if let [only] = batch {
+        return dispatch(&Event {
+            interface: &only.interface,
+            unit: &only.unit,
+            elapsed: only.elapsed,
+            timeout: only.timeout,
+            outcome: &only.outcome,
+        });
+    }
+
+    let join = |field: fn(&Pending) -> &str| batch.iter().map(field).collect::<Vec<_>>().join(", ");
+    let interfaces = join(|pending| &pending.interface);
+    let units = join(|pending| &pending.unit);
+
+    // Counted in the order outcomes first appeared, e.g. "2 restarted, 1 restart failed"
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+
+    for pending in batch {
+        match counts.iter_mut().find(|(outcome, _)| *outcome == pending.outcome) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((&pending.outcome, 1)),
+        }
+    }
+
+    let counts = counts.iter().map(|(outcome, count)| format!("{count} {outcome}")).collect::<Vec<_>>().join(", ");
+    let outcome = format!("{} tunnels down; {counts}", batch.len());
+
+    dispatch(&Event {
+        interface: &interfaces,
+        unit: &units,
+        elapsed: batch.iter().filter_map(|pending| pending.elapsed).max(),
+        timeout: batch[0].timeout,
+        outcome: &outcome,
+    });
+}
@@ -0,0 +1,35 @@
+//! Age of handshake timestamps against the local clock, and reading the timestamps systemd prints.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How long before `now` a Unix timestamp was.
+///
+/// A timestamp after `now` means the system clock has jumped backwards since
+/// it was recorded. Rather than passing that off as zero elapsed, which is
+/// indistinguishable from a handshake that just happened, how far ahead of
+/// `now` the timestamp is gets returned as the error.
+pub fn age(now: SystemTime, timestamp: u64) -> Result<Duration, Duration> {
+    now.duration_since(UNIX_EPOCH + Duration::from_secs(timestamp))
+        .map_err(|e| e.duration())
+}
+
+/// Parse a systemd timestamp property value, as printed by `systemctl show`.
+///
+/// With `--timestamp=unix` (systemd 248 and later) this is `@SECONDS`. Older
+/// versions print a local time like `Tue 2026-10-14 12:00:00 CEST`, which is only
+/// unambiguous, and so only accepted, when the zone is UTC. An empty value
+/// means the unit never entered the state in question.
+pub fn parse_systemd_timestamp(s: &str) -> Option<SystemTime> {
+    let s = s.trim();
+
+    if let Some(secs) = s.strip_prefix('@') {
+        // May carry a fractional part, e.g. "@1697280000.123456"
+        let secs = secs.split('.').next()?.parse().ok()?;
+        return Some(UNIX_EPOCH + Duration::from_secs(secs));
+    }
+
+    match s.split_whitespace().collect::<Vec<_>>()[..] {
+        [_weekday, date, clock, "UTC"] => humantime::parse_rfc3339_weak(&format!("{date} {clock}")).ok(),
+        _ => None,
+    }
+}
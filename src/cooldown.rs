@@ -0,0 +1,53 @@
+//! `--notify-cooldown`, which holds back notifications about an event type
+//! notified about recently, and counts those held back.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// When an event type was last notified about, and how many since were not.
+struct CooledEvent {
+    notified_at: Instant,
+    suppressed: u32,
+}
+
+/// Notifications sent per event type.
+#[derive(Default)]
+pub struct NotifyCooldown {
+    events: HashMap<String, CooledEvent>,
+}
+
+impl NotifyCooldown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether to notify about `outcome` at `now`, or count it as suppressed if it was notified about within `cooldown`.
+    pub fn admit(&mut self, outcome: &str, cooldown: Duration, now: Instant) -> bool {
+        if let Some(event) = self.events.get_mut(outcome)
+            && now.saturating_duration_since(event.notified_at) < cooldown
+        {
+            event.suppressed += 1;
+            return false;
+        }
+
+        self.events.insert(outcome.to_string(), CooledEvent { notified_at: now, suppressed: 0 });
+        true
+    }
+
+    /// Forget event types whose cooldown has passed by `now`, returning those that had notifications suppressed and how many.
+    pub fn expired(&mut self, cooldown: Duration, now: Instant) -> Vec<(String, u32)> {
+        let mut expired = Vec::new();
+
+        self.events.retain(|outcome, event| {
+            let active = now.saturating_duration_since(event.notified_at) < cooldown;
+
+            if !active && event.suppressed > 0 {
+                expired.push((outcome.clone(), event.suppressed));
+            }
+
+            active
+        });
+
+        expired
+    }
+}
@@ -0,0 +1,77 @@
+//! `--credential`, which reads further options from a systemd credential, as
+//! passed to a service with `LoadCredential=` and exposed as a file under
+//! `$CREDENTIALS_DIRECTORY`.
+
+use crate::error::Error;
+use std::{env, ffi, fs, path};
+
+/// Environment variable systemd points at the directory of a service's credentials.
+pub const DIRECTORY_ENV_VAR: &str = "CREDENTIALS_DIRECTORY";
+
+/// Find the value of `--credential` in the raw command line, ahead of parsing it.
+pub fn name(args: &[ffi::OsString]) -> Option<String> {
+    let mut args = args.iter().filter_map(|arg| arg.to_str());
+
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            break;
+        }
+
+        if arg == "--credential" {
+            return args.next().map(str::to_string);
+        }
+
+        if let Some(name) = arg.strip_prefix("--credential=") {
+            return Some(name.to_string());
+        }
+    }
+
+    None
+}
+
+/// Read the options in credential `name`, or `None` if systemd passed no credentials.
+pub fn load(name: &str) -> Result<Option<(path::PathBuf, Vec<String>)>, Error> {
+    let directory = match env::var_os(DIRECTORY_ENV_VAR) {
+        Some(directory) => path::PathBuf::from(directory),
+        None => return Ok(None),
+    };
+
+    let path = directory.join(name);
+    let contents = fs::read_to_string(&path).map_err(|source| Error::Read { path: path.clone(), source })?;
+
+    let options = parse(&contents).map_err(|(line, reason)| Error::Credential { path: path.clone(), line, reason })?;
+    Ok(Some((path, options)))
+}
+
+/// Turn `option = value` lines, and bare `option` lines for flags, into `--option=value` arguments.
+///
+/// Blank lines and lines starting with `#` are skipped. Errors carry the line number.
+fn parse(contents: &str) -> Result<Vec<String>, (usize, String)> {
+    let mut options = Vec::new();
+
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (option, value) = match line.split_once('=') {
+            Some((option, value)) => (option.trim(), Some(value.trim())),
+            None => (line, None),
+        };
+
+        let option = option.strip_prefix("--").unwrap_or(option);
+
+        if option.is_empty() || option.contains(char::is_whitespace) {
+            return Err((number + 1, format!("expected `option = value` or `option`, got `{line}`")));
+        }
+
+        options.push(match value {
+            Some(value) => format!("--{option}={value}"),
+            None => format!("--{option}"),
+        });
+    }
+
+    Ok(options)
+}
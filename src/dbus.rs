@@ -0,0 +1,152 @@
+//! Checking and restarting systemd units over D-Bus, for `--backend dbus`.
+//!
+//! This talks to systemd's manager object on the system bus directly instead
+//! of spawning `systemctl` on every loop, and only works locally.
+
+use crate::SystemctlVerb;
+use crate::error::Error;
+use futures_lite::{StreamExt, future};
+use std::time;
+use zbus::{blocking, proxy::CacheProperties, zvariant::OwnedObjectPath};
+
+const DESTINATION: &str = "org.freedesktop.systemd1";
+const MANAGER_PATH: &str = "/org/freedesktop/systemd1";
+const MANAGER_INTERFACE: &str = "org.freedesktop.systemd1.Manager";
+const UNIT_INTERFACE: &str = "org.freedesktop.systemd1.Unit";
+
+/// Returned by `GetUnit` for units that are not loaded, which `systemctl is-active` reports as `inactive`.
+const NO_SUCH_UNIT: &str = "org.freedesktop.systemd1.NoSuchUnit";
+
+/// A connection to the systemd manager on the system bus.
+pub struct Manager {
+    connection: blocking::Connection,
+}
+
+impl Manager {
+    /// Connect to the system bus, and subscribe to the job signals restarts wait for.
+    pub fn connect() -> Result<Self, Error> {
+        let connection = blocking::Connection::system().map_err(|e| Error::DbusConnect(Box::new(e)))?;
+        let manager = Self { connection };
+
+        // systemd only emits job signals to clients that subscribed
+        manager
+            .proxy(MANAGER_PATH, MANAGER_INTERFACE)
+            .and_then(|proxy| proxy.call_method("Subscribe", &()))
+            .map_err(|e| Error::dbus("Subscribe", e))?;
+
+        Ok(manager)
+    }
+
+    /// Build a proxy for `interface` on the object at `path`, reading properties afresh on every access.
+    fn proxy<'p>(&self, path: impl Into<String>, interface: &'p str) -> zbus::Result<blocking::Proxy<'p>> {
+        blocking::proxy::Builder::new(&self.connection)
+            .destination(DESTINATION)?
+            .path(path.into())?
+            .interface(interface)?
+            .cache_properties(CacheProperties::No)
+            .build()
+    }
+
+    /// Get a proxy for a loaded unit, or `None` if it is not loaded.
+    fn unit(&self, unit_name: &str) -> Result<Option<blocking::Proxy<'_>>, Error> {
+        let manager = self.proxy(MANAGER_PATH, MANAGER_INTERFACE).map_err(|e| Error::dbus("GetUnit", e))?;
+
+        let path: OwnedObjectPath = match manager.call("GetUnit", &(unit_name,)) {
+            Ok(path) => path,
+            Err(zbus::Error::MethodError(name, _, _)) if name.as_str() == NO_SUCH_UNIT => return Ok(None),
+            Err(e) => return Err(Error::dbus("GetUnit", e)),
+        };
+
+        self.proxy(path.as_str(), UNIT_INTERFACE)
+            .map(Some)
+            .map_err(|e| Error::dbus("GetUnit", e))
+    }
+
+    /// Get the `ActiveState` of a unit, e.g. `active` or `failed`.
+    pub fn active_state(&self, unit_name: &str) -> Result<String, Error> {
+        match self.unit(unit_name)? {
+            Some(unit) => unit.get_property("ActiveState").map_err(|e| Error::dbus("ActiveState", e)),
+            None => Ok("inactive".to_string()),
+        }
+    }
+
+    /// Get the time a unit last entered the active state, if it has.
+    pub fn active_since(&self, unit_name: &str) -> Result<Option<time::SystemTime>, Error> {
+        let Some(unit) = self.unit(unit_name)? else { return Ok(None) };
+
+        // Microseconds since the epoch, or 0 if never
+        let usecs: u64 = unit.get_property("ActiveEnterTimestamp").map_err(|e| Error::dbus("ActiveEnterTimestamp", e))?;
+        Ok((usecs > 0).then(|| time::UNIX_EPOCH + time::Duration::from_micros(usecs)))
+    }
+
+    /// Send `signal` to the main process of a unit, like `systemctl kill --kill-whom=main` does.
+    pub fn kill(&self, unit_name: &str, signal: i32) -> Result<(), Error> {
+        let manager = self.proxy(MANAGER_PATH, MANAGER_INTERFACE).map_err(|e| Error::dbus("KillUnit", e))?;
+
+        manager
+            .call_method("KillUnit", &(unit_name, "main", signal))
+            .map(|_| ())
+            .map_err(|e| Error::dbus("KillUnit", e))
+    }
+
+    /// Queue a restart of a unit the way `verb` says, without waiting for it to finish, like `systemctl --no-block restart` does.
+    pub fn restart_no_block(&self, unit_name: &str, verb: SystemctlVerb) -> Result<(), Error> {
+        let method = verb.dbus_method();
+        let manager = self.proxy(MANAGER_PATH, MANAGER_INTERFACE).map_err(|e| Error::dbus(method, e))?;
+
+        manager
+            .call::<_, _, OwnedObjectPath>(method, &(unit_name, "replace"))
+            .map(|_| ())
+            .map_err(|e| Error::dbus(method, e))
+    }
+
+    /// Restart a unit the way `verb` says and wait for the job to finish, like `systemctl restart` does.
+    ///
+    /// With a `timeout`, waiting is given up on after that long. The job
+    /// itself is left to systemd to finish.
+    pub fn restart(&self, unit_name: &str, verb: SystemctlVerb, timeout: Option<time::Duration>) -> Result<(), Error> {
+        let method = verb.dbus_method();
+        let manager = self.proxy(MANAGER_PATH, MANAGER_INTERFACE).map_err(|e| Error::dbus(method, e))?;
+
+        // The job may finish before the method returns, so listen beforehand
+        let mut removed = async_io::block_on(manager.inner().receive_signal("JobRemoved")).map_err(|e| Error::dbus("JobRemoved", e))?;
+
+        let job: OwnedObjectPath = manager
+            .call(method, &(unit_name, "replace"))
+            .map_err(|e| Error::dbus(method, e))?;
+
+        let wait = async {
+            while let Some(signal) = removed.next().await {
+                let (_id, path, _unit, result): (u32, OwnedObjectPath, String, String) = signal
+                    .body()
+                    .deserialize()
+                    .map_err(|e| Error::dbus("JobRemoved", e))?;
+
+                if path == job {
+                    return Ok(Some(result));
+                }
+            }
+
+            Err(Error::dbus("JobRemoved", zbus::Error::Failure("connection closed before the job finished".to_string())))
+        };
+
+        // Giving up on waiting drops the signal stream along with it
+        let deadline = async {
+            match timeout {
+                Some(timeout) => async_io::Timer::after(timeout).await,
+                None => future::pending().await,
+            };
+
+            Ok(None)
+        };
+
+        let Some(result) = async_io::block_on(future::or(wait, deadline))? else {
+            return Err(Error::Timeout { command: format!("{method} {unit_name}"), timeout: timeout.unwrap_or_default() });
+        };
+
+        match result.as_str() {
+            "done" => Ok(()),
+            _ => Err(Error::Job { verb: verb.command(), unit: unit_name.to_string(), result }),
+        }
+    }
+}
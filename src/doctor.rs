@@ -0,0 +1,269 @@
+//! The `doctor` subcommand, which checks that everything monitoring an
+//! interface depends on is in place, and reports on each check.
+
+use crate::error::Error;
+use crate::output;
+use crate::runner::CommandRunner;
+use crate::{Backend, Cli, Reading};
+use log::{error, info};
+use std::{fmt, process};
+use wg_restarter::glob;
+
+/// Outcome of one check.
+#[derive(PartialEq, Eq)]
+enum Status {
+    Pass,
+
+    /// Not necessarily a problem, but worth looking into
+    Warn,
+
+    /// Monitoring will not work
+    Fail,
+
+    /// Does not apply to this configuration
+    Skip,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Pass => "pass",
+            Self::Warn => "warn",
+            Self::Fail => "fail",
+            Self::Skip => "skip",
+        })
+    }
+}
+
+/// One line of the report.
+struct Check {
+    name: &'static str,
+    status: Status,
+    detail: String,
+}
+
+impl Check {
+    fn new(name: &'static str, status: Status, detail: impl Into<String>) -> Self {
+        Self { name, status, detail: detail.into() }
+    }
+
+    /// A check that passes with `Ok` and fails with `Err`, either way with a detail.
+    fn from_result(name: &'static str, result: Result<String, impl fmt::Display>) -> Self {
+        match result {
+            Ok(detail) => Self::new(name, Status::Pass, detail),
+            Err(e) => Self::new(name, Status::Fail, e.to_string()),
+        }
+    }
+}
+
+/// Get the first line of a command's output, failing if it did not run successfully.
+fn first_line(runner: &CommandRunner, program: &str, args: &[&str]) -> Result<String, Error> {
+    let output = runner.command(program, args)
+        .output()
+        .map_err(|e| Error::spawn(runner.spawned(program), e))?;
+
+    if !output.status.success() {
+        return Err(Error::exit(&format!("{program} {}", args.join(" ")), &output));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).lines().next().unwrap_or_default().trim().to_string())
+}
+
+/// Check that the systemd unit exists and is running.
+fn check_unit(runner: &CommandRunner, unit_name: &str) -> Result<String, String> {
+    let load_state = first_line(runner, "systemctl", &["show", "-p", "LoadState", "--value", unit_name])
+        .map_err(|e| e.to_string())?;
+
+    match load_state.as_str() {
+        "loaded" => {},
+        "not-found" => return Err(format!("`{unit_name}` does not exist")),
+        state => return Err(format!("`{unit_name}` could not be loaded ({state})")),
+    }
+
+    match crate::get_systemd_unit_state(runner, None, unit_name).map_err(|e| e.to_string())? {
+        state if state.is_active() => Ok(format!("`{unit_name}` is {state}")),
+        state => Err(format!("`{unit_name}` is {state}, but must be active when monitoring starts")),
+    }
+}
+
+/// Check whether `systemctl restart` is likely to be allowed.
+///
+/// There is no way to test a restart without doing one, so this only checks for root.
+fn check_restart_permission(runner: &CommandRunner) -> Check {
+    const NAME: &str = "restart permission";
+
+    match crate::get_euid(runner) {
+        Ok(0) => Check::new(NAME, Status::Pass, "running as root"),
+        Ok(uid) => Check::new(NAME, Status::Warn, format!("running as uid {uid}; `systemctl restart` will be refused unless polkit allows it")),
+        Err(e) => Check::new(NAME, Status::Warn, e.to_string()),
+    }
+}
+
+/// Whether `program` can be found by the shell `runner` runs commands in.
+fn resolves(runner: &CommandRunner, program: &str) -> bool {
+    runner.command("sh", &["-c", "command -v -- \"$0\" >/dev/null 2>&1", program])
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Check that the program of every configured command can be found.
+///
+/// Commands that act on the interface are looked for where the interface is,
+/// notifications and hooks locally.
+fn check_commands(cli: &Cli, runner: &CommandRunner) -> Check {
+    const NAME: &str = "commands";
+
+    let local = CommandRunner::new(None);
+    let commands = [
+        ("--restart-command", cli.restart_command.as_deref(), runner),
+        ("--up-check-command", cli.up_check_command.as_deref(), runner),
+        ("--reboot-command", cli.reboot_after.map(|_| cli.reboot_command.as_str()), runner),
+        ("--notify-command", cli.notify_command.as_deref(), &local),
+        ("--on-pause-command", cli.on_pause_command.as_deref(), &local),
+        ("--on-resume-command", cli.on_resume_command.as_deref(), &local),
+    ];
+
+    let mut found = 0;
+    let mut missing = Vec::new();
+
+    for (flag, command_line, runner) in commands {
+        let Some(program) = command_line.and_then(|command_line| command_line.split_whitespace().next()) else {
+            continue;
+        };
+
+        if resolves(runner, program) {
+            found += 1;
+        } else {
+            missing.push(format!("`{program}` of {flag}"));
+        }
+    }
+
+    match (found, missing.is_empty()) {
+        (0, true) => Check::new(NAME, Status::Skip, "no commands configured"),
+        (_, true) => Check::new(NAME, Status::Pass, format!("{found} command(s) found")),
+        (_, false) => Check::new(NAME, Status::Fail, format!("not found: {}", missing.join(", "))),
+    }
+}
+
+/// Run all checks, in the order monitoring would depend on them.
+fn checks(cli: &Cli, runner: &CommandRunner, interface: &str) -> Vec<Check> {
+    let unit_name = cli.unit_name(interface);
+    let mut checks = vec![Check::from_result("wg", first_line(runner, "wg", &["--version"]))];
+
+    checks.push(match cli.backend {
+        Backend::Systemd => Check::from_result("systemctl", first_line(runner, "systemctl", &["--version"])),
+        Backend::Dbus => Check::from_result("dbus", crate::dbus::Manager::connect()
+            .map(|_| "connected to the system bus".to_string())),
+        Backend::Script => Check::new("systemctl", Status::Skip, "not used by the script backend"),
+    });
+
+    checks.push(Check::from_result("interface", crate::get_wg_show(runner, interface, "peers")
+        .map(|peers| format!("`{interface}` has {} peer(s)", peers.split(|&b| b == b'\n').filter(|line| !line.is_empty()).count()))));
+
+    checks.push(match cli.backend {
+        Backend::Systemd | Backend::Dbus => Check::from_result("unit", check_unit(runner, &unit_name)),
+        Backend::Script => {
+            let up_check_command = cli.up_check_command.as_deref().expect("clap requires --up-check-command");
+
+            Check::from_result("unit", match crate::get_script_is_up(runner, up_check_command, interface) {
+                Ok(true) => Ok("up-check command reports the interface is up".to_string()),
+                Ok(false) => Err("up-check command reports the interface is not up".to_string()),
+                Err(e) => Err(e.to_string()),
+            })
+        }
+    });
+
+    checks.push(check_commands(cli, runner));
+
+    checks.push(match cli.restart_command {
+        Some(_) => Check::new("restart permission", Status::Skip, "--restart-command is not run, to avoid a restart"),
+        None => check_restart_permission(runner),
+    });
+
+    const HANDSHAKE: &str = "handshake";
+
+    checks.push(match crate::get_handshake_ts(cli, runner, interface, cli.peer.as_deref()) {
+        Ok(Reading::Value(timestamp)) => Check::new(HANDSHAKE, Status::Pass, format!("latest handshake {}", crate::describe_handshake_age(timestamp))),
+        Ok(Reading::PeerAbsent) => Check::new(HANDSHAKE, Status::Fail, format!("peer {} given with --peer is not a peer of `{interface}`",
+            cli.peer_key(cli.peer.as_deref().unwrap_or_default()))),
+        Ok(Reading::NoPeers) => Check::new(HANDSHAKE, Status::Warn, format!("`{interface}` has no peers configured")),
+        Err(e) => Check::new(HANDSHAKE, Status::Fail, e.to_string()),
+    });
+
+    checks
+}
+
+/// Report lines for `checks`, with `interface` if more than one may be reported on.
+fn records(checks: &[Check], interface: Option<&str>) -> Vec<output::Record> {
+    checks
+        .iter()
+        .map(|check| {
+            let mut record = output::Record::new();
+
+            if let Some(interface) = interface {
+                record.insert("interface".into(), interface.into());
+            }
+
+            record.insert("check".into(), check.name.into());
+            record.insert("status".into(), check.status.to_string().into());
+            record.insert("detail".into(), check.detail.as_str().into());
+            record
+        })
+        .collect()
+}
+
+/// Run all checks and print a report, failing if any check did.
+pub fn run(cli: &Cli, interface: &str) -> process::ExitCode {
+    let runner = CommandRunner::new(cli.ssh.clone());
+    let checks = checks(cli, &runner, interface);
+
+    print!("{}", output::records(cli.output_format, &records(&checks, None)));
+
+    if checks.iter().any(|check| check.status == Status::Fail) {
+        process::ExitCode::FAILURE
+    } else {
+        process::ExitCode::SUCCESS
+    }
+}
+
+/// `--check-config`: run all checks for `interface`, or with `None` for every interface `--all` or an interface pattern would monitor.
+///
+/// Everything validated at startup already has been by the time this is
+/// called, so only what depends on the host is left to check.
+pub fn check_config(cli: &Cli, interface: Option<&str>) -> process::ExitCode {
+    let runner = CommandRunner::new(cli.ssh.clone());
+
+    let interfaces = match interface {
+        Some(interface) => vec![interface.to_string()],
+        None => match crate::get_wg_interfaces(&runner) {
+            Ok(mut interfaces) => {
+                interfaces.retain(|interface| cli.interface_pattern().is_none_or(|pattern| glob::matches(pattern, interface)));
+                interfaces.retain(|interface| !cli.ignore_interface.iter().any(|pattern| glob::matches(pattern, interface)));
+                interfaces
+            },
+            Err(e) => {
+                error!("{e}; exiting ...");
+                return process::ExitCode::FAILURE;
+            },
+        },
+    };
+
+    let mut failed = false;
+    let mut report = Vec::new();
+
+    for interface in &interfaces {
+        let checks = checks(cli, &runner, interface);
+        failed |= checks.iter().any(|check| check.status == Status::Fail);
+        report.extend(records(&checks, Some(interface)));
+    }
+
+    print!("{}", output::records(cli.output_format, &report));
+
+    if failed {
+        error!("configuration has problems; see the failed checks above ...");
+        process::ExitCode::FAILURE
+    } else {
+        info!("configuration is valid for {} interface(s) ...", interfaces.len());
+        process::ExitCode::SUCCESS
+    }
+}
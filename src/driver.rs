@@ -0,0 +1,114 @@
+//! `--stdin-driver`, which applies the staleness logic to handshake
+//! timestamps read from stdin and writes out decisions, leaving the actual
+//! restarting to whatever is feeding it.
+
+use crate::{Cli, HandshakeAge, Staleness};
+use log::{debug, error, warn};
+use std::io::{BufRead, Write};
+use std::{collections, io, process, time};
+
+/// What is known about one interface from earlier lines.
+struct Driven {
+    /// Since when no handshake has been seen, for `--first-handshake-timeout`
+    waiting_since: time::Instant,
+
+    /// When `restart` was last written, until a fresh handshake is seen
+    restarted_at: Option<time::Instant>,
+}
+
+/// Decide whether `interface` should be restarted given its latest handshake `timestamp`.
+fn decide(cli: &Cli, driven: &mut Driven, interface: &str, timestamp: u64) -> bool {
+    let now = time::Instant::now();
+
+    let stale = if timestamp == 0 {
+        cli.first_handshake_timeout
+            .is_some_and(|timeout| now.duration_since(driven.waiting_since) > timeout)
+    } else {
+        match cli.on_clock_anomaly.age(time::SystemTime::now(), timestamp, &format!("{interface}: latest handshake")) {
+            HandshakeAge::Age(elapsed) => {
+                debug!("{interface}: handshake age {}s, timeout {}s", elapsed.as_secs(), cli.timeout.as_secs());
+
+                match cli.staleness(elapsed) {
+                    Staleness::Fresh => false,
+                    Staleness::InDeadband(deadband) => {
+                        debug!("{interface}: within the deadband of {}s; not restarting ...", deadband.as_secs());
+                        return false;
+                    },
+                    Staleness::Stale => true,
+                }
+            },
+            HandshakeAge::Restart => true,
+
+            // Neither fresh nor stale, so a restart in flight is left as it is
+            HandshakeAge::Skip => return false,
+        }
+    };
+
+    if !stale {
+        if timestamp != 0 {
+            driven.restarted_at = None;
+        }
+
+        return false;
+    }
+
+    // Leave a restart that was just asked for time to take effect
+    if let Some(restarted_at) = driven.restarted_at
+        && now.duration_since(restarted_at) < cli.restart_in_flight
+    {
+        debug!("{interface}: restart still in flight ...");
+        return false;
+    }
+
+    driven.restarted_at = Some(now);
+    driven.waiting_since = now;
+    true
+}
+
+/// Read `interface<TAB>handshake_ts` lines from stdin until it is closed, writing `restart <interface>` or `ok <interface>` for each.
+pub fn run(cli: &Cli) -> process::ExitCode {
+    let mut interfaces: collections::HashMap<String, Driven> = collections::HashMap::new();
+    let mut stdout = io::stdout().lock();
+
+    for line in io::stdin().lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                error!("failed to read stdin: {e}; exiting ...");
+                return process::ExitCode::FAILURE;
+            },
+        };
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let Some((interface, timestamp)) = line
+            .split_once('\t')
+            .and_then(|(interface, ts)| Some((interface.trim(), ts.trim().parse::<u64>().ok()?)))
+            .filter(|(interface, _)| !interface.is_empty())
+        else {
+            if cli.fail_fast {
+                error!("unexpected line on stdin: `{line}`; exiting due to --fail-fast ...");
+                return process::ExitCode::FAILURE;
+            }
+
+            warn!("unexpected line on stdin: `{line}`; skipping ...");
+            continue;
+        };
+
+        let driven = interfaces.entry(interface.to_string()).or_insert_with(|| Driven {
+            waiting_since: time::Instant::now(),
+            restarted_at: None,
+        });
+
+        let decision = if decide(cli, driven, interface, timestamp) { "restart" } else { "ok" };
+
+        if writeln!(stdout, "{decision} {interface}").and_then(|()| stdout.flush()).is_err() {
+            // Whoever was reading decisions has gone away
+            return process::ExitCode::FAILURE;
+        }
+    }
+
+    process::ExitCode::SUCCESS
+}
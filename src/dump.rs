@@ -0,0 +1,92 @@
+//! Diagnostic snapshots of an interface, written to disk before a restart.
+
+use crate::runner::CommandRunner;
+use std::{collections, fs, io, path, time};
+
+/// Run a command and format its output as one section of a dump.
+fn section(runner: &CommandRunner, program: &str, args: &[&str]) -> String {
+    let header = format!("# {program} {}\n", args.join(" "));
+
+    let body = match runner.command(program, args).output() {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout).into_owned(),
+        Ok(output) => format!("(exited with status {}) {}\n",
+            output.status.code().unwrap_or(-1),
+            String::from_utf8_lossy(&output.stderr).trim()),
+        Err(e) => format!("(failed to run: {e})\n"),
+    };
+
+    format!("{header}{body}\n")
+}
+
+/// Collect the state of an interface into one human-readable report.
+fn collect(runner: &CommandRunner, interface: &str) -> String {
+    let mut report = String::new();
+
+    report += &section(runner, "wg", &["show", interface]);
+    report += &section(runner, "wg", &["show", interface, "dump"]);
+    report += &section(runner, "ip", &["route", "show", "table", "all"]);
+
+    // How each peer endpoint is currently routed, "ADDRESS:PORT" or "[ADDRESS]:PORT"
+    let endpoints = runner.command("wg", &["show", interface, "endpoints"])
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+        .unwrap_or_default();
+
+    // Peers can share an endpoint, so look up each address only once
+    let mut routed = collections::HashSet::new();
+
+    for line in endpoints.lines() {
+        let Some((_, endpoint)) = line.split_once('\t') else { continue };
+        let Some((address, _)) = endpoint.rsplit_once(':') else { continue };  // "(none)" has no port
+        let address = address.trim_start_matches('[').trim_end_matches(']');
+
+        if routed.insert(address) {
+            report += &section(runner, "ip", &["route", "get", address]);
+        }
+    }
+
+    report
+}
+
+/// Write a dump of an interface to a timestamped file in `dir`, then delete
+/// all but the `keep` newest dumps of that interface. Returns the path written.
+pub fn write(runner: &CommandRunner, dir: &path::Path, interface: &str, keep: usize) -> io::Result<path::PathBuf> {
+    let timestamp = humantime::format_rfc3339_seconds(time::SystemTime::now());
+    let path = dir.join(format!("{interface}-{timestamp}.txt"));
+
+    fs::create_dir_all(dir)?;
+    fs::write(&path, collect(runner, interface))?;
+    rotate(dir, interface, keep)?;
+
+    Ok(path)
+}
+
+/// Delete all but the `keep` newest dumps of an interface.
+///
+/// Only files named as [`write`] names them are considered, so that the dumps
+/// of an interface whose name starts with this one's, like `wg0-backup` for
+/// `wg0`, are left alone.
+fn rotate(dir: &path::Path, interface: &str, keep: usize) -> io::Result<()> {
+    let mut dumps = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let name = entry?.file_name();
+        let Some(name) = name.to_str() else { continue };
+
+        if let Some(timestamp) = name.strip_prefix(interface).and_then(|rest| rest.strip_prefix('-'))
+            .and_then(|rest| rest.strip_suffix(".txt"))
+            && humantime::parse_rfc3339(timestamp).is_ok()
+        {
+            dumps.push(name.to_string());
+        }
+    }
+
+    // RFC 3339 timestamps sort chronologically as strings
+    dumps.sort_unstable();
+
+    for name in dumps.iter().rev().skip(keep) {
+        fs::remove_file(dir.join(name))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,12 @@
+//! Parsing of durations given on the command line.
+
+use std::time;
+
+/// Parse a duration given either as a bare number of seconds (`600`) or as a
+/// `humantime` duration (`600s`, `10m`, `1h 30m`).
+pub fn parse(s: &str) -> Result<time::Duration, humantime::DurationError> {
+    match s.trim().parse() {
+        Ok(secs) => Ok(time::Duration::from_secs(secs)),
+        Err(_) => humantime::parse_duration(s),
+    }
+}
@@ -0,0 +1,62 @@
+//! Tracking of peer endpoints across checks, for `--endpoint-change-detect`.
+
+use crate::wg;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Endpoint of one peer as last seen, and when it changed within the window.
+struct PeerEndpoint {
+    endpoint: String,
+    changes: VecDeque<SystemTime>,
+}
+
+/// Endpoints of all peers as last seen.
+///
+/// Keyed by public key, never by endpoint, as several peers can sit behind
+/// the same one; a change is attributed only to the peer whose endpoint moved.
+#[derive(Default)]
+pub struct EndpointHistory {
+    peers: HashMap<String, PeerEndpoint>,
+}
+
+impl EndpointHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the endpoints of `peers` as of `now`, forgetting changes older than `window` and peers that are gone.
+    ///
+    /// Returns the public key of a peer whose endpoint changed more than `max`
+    /// times within the window without a handshake since the first of those
+    /// changes, along with how many times it changed.
+    pub fn update<'p>(&mut self, now: SystemTime, peers: &[wg::Peer<'p>], window: Duration, max: u32) -> Option<(&'p str, usize)> {
+        self.peers.retain(|public_key, _| peers.iter().any(|peer| peer.public_key == public_key));
+
+        let mut flapping = None;
+
+        for peer in peers {
+            let seen = self.peers
+                .entry(peer.public_key.to_string())
+                .or_insert_with(|| PeerEndpoint { endpoint: peer.endpoint.to_string(), changes: VecDeque::new() });
+
+            if seen.endpoint != peer.endpoint {
+                seen.endpoint = peer.endpoint.to_string();
+                seen.changes.push_back(now);
+            }
+
+            while seen.changes.front().is_some_and(|&t| now.duration_since(t).unwrap_or_default() > window) {
+                seen.changes.pop_front();
+            }
+
+            let handshaked_since = seen.changes
+                .front()
+                .is_some_and(|&first| UNIX_EPOCH + Duration::from_secs(peer.latest_handshake) >= first);
+
+            if seen.changes.len() > max as usize && !handshaked_since {
+                flapping.get_or_insert((peer.public_key, seen.changes.len()));
+            }
+        }
+
+        flapping
+    }
+}
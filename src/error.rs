@@ -0,0 +1,168 @@
+//! Errors from running external commands and interpreting what they output.
+
+use std::{env, io, path, process, time};
+
+/// Something that went wrong inspecting or acting on the connection.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The program is not installed, or not in `PATH`
+    #[error("`{0}` not found; is it installed?")]
+    MissingBinary(String),
+
+    /// The program exists but may not be run
+    #[error("permission denied running `{0}`")]
+    PermissionDenied(String),
+
+    /// The program could not be started for some other reason
+    #[error("failed to run `{program}`: {source}")]
+    Spawn { program: String, source: io::Error },
+
+    /// The command ran but exited unsuccessfully
+    #[error("`{command}` returned {status}: {stderr}")]
+    Exit { command: String, status: i32, stderr: String },
+
+    /// `systemctl` ran but could not reach the systemd manager, typically only for a moment under load or early in boot
+    #[error("`{command}` could not reach systemd: {stderr}")]
+    SystemdUnreachable { command: String, stderr: String },
+
+    /// The command succeeded, but its output was not understood
+    #[error("unexpected `{command}` output:\n{output}")]
+    Parse { command: String, output: String },
+
+    /// The `--handshake-column` could not be read from the output of `command`
+    #[error("unexpected `{command}` output: {source}")]
+    Column { command: String, source: wg_restarter::wg::ColumnError },
+
+    /// There are not enough peers for `--min-fresh-peers` to ever be met
+    #[error("interface has {peers} peer(s), fewer than --min-fresh-peers {quorum}")]
+    TooFewPeers { peers: usize, quorum: u32 },
+
+    /// `--peer-name` does not name any peer in the config
+    #[error("no peer named `{name}` in `{}`", path.display())]
+    UnknownPeerName { name: String, path: path::PathBuf },
+
+    /// A file could not be read
+    #[error("failed to read `{}`: {source}", path.display())]
+    Read { path: path::PathBuf, source: io::Error },
+
+    /// A file could not be opened for writing
+    #[error("failed to open `{}` for writing: {source}", path.display())]
+    Write { path: path::PathBuf, source: io::Error },
+
+    /// A `--credential` could not be understood
+    #[error("invalid line {line} in credential `{}`: {reason}", path.display())]
+    Credential { path: path::PathBuf, line: usize, reason: String },
+
+    /// A `--state-file` could not be understood
+    #[error("invalid state file `{}`: {reason}", path.display())]
+    StateFile { path: path::PathBuf, reason: String },
+
+    /// An environment variable is set but not valid unicode
+    #[error("invalid `{var}`: {source}")]
+    Env { var: String, source: env::VarError },
+
+    /// A secret was provided, but is empty
+    #[error("{0} is empty")]
+    EmptySecret(String),
+
+    /// A notification could not be posted, even after retrying
+    #[error("failed to post notification to `{url}` after {attempts} attempt(s): {source}")]
+    Http { url: String, attempts: u32, source: ureq::Error },
+
+    /// The thread posting a notification panicked before it was done
+    #[error("posting notification to `{url}` panicked")]
+    HttpPanicked { url: String },
+
+    /// The system bus could not be connected to
+    #[error("failed to connect to the system bus: {0}")]
+    DbusConnect(Box<zbus::Error>),
+
+    /// A D-Bus call to systemd failed
+    #[error("D-Bus `{call}` failed: {source}")]
+    Dbus { call: String, source: Box<zbus::Error> },
+
+    /// A systemd job ran, but did not succeed
+    #[error("`{verb}` of `{unit}` finished with result `{result}`")]
+    Job { verb: &'static str, unit: String, result: String },
+
+    /// A restart did not finish within `--restart-timeout`
+    #[error("`{command}` did not finish within {}s", timeout.as_secs())]
+    Timeout { command: String, timeout: time::Duration },
+}
+
+impl Error {
+    /// Classify a failure to start `program`.
+    pub fn spawn(program: &str, source: io::Error) -> Self {
+        match source.kind() {
+            io::ErrorKind::NotFound => Self::MissingBinary(program.to_string()),
+            io::ErrorKind::PermissionDenied => Self::PermissionDenied(program.to_string()),
+            _ => Self::Spawn { program: program.to_string(), source },
+        }
+    }
+
+    /// Classify an unsuccessful exit of `command`.
+    ///
+    /// Shells, and so ssh, exit with 127 when the command is not found.
+    pub fn exit(command: &str, output: &process::Output) -> Self {
+        match output.status.code() {
+            Some(127) => Self::MissingBinary(command.split_whitespace().next().unwrap_or(command).to_string()),
+            status => Self::Exit {
+                command: command.to_string(),
+                status: status.unwrap_or(-1),
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            },
+        }
+    }
+
+    /// A failed D-Bus `call`.
+    pub fn dbus(call: &str, source: zbus::Error) -> Self {
+        Self::Dbus { call: call.to_string(), source: Box::new(source) }
+    }
+
+    /// Classify an unsuccessful exit of a `systemctl` command, telling failing to reach systemd apart.
+    ///
+    /// `systemctl` exits with 1 whether the bus was unreachable or the unit
+    /// failed, so the two can only be told apart by what it printed.
+    pub fn systemctl(command: &str, output: &process::Output) -> Self {
+        const UNREACHABLE: &[&str] = &[
+            "Failed to connect to bus",
+            "Failed to connect to system scope bus",
+            "Failed to get D-Bus connection",
+            "Transport endpoint is not connected",
+            "Connection timed out",
+        ];
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        match UNREACHABLE.iter().any(|message| stderr.contains(message)) {
+            true => Self::SystemdUnreachable { command: command.to_string(), stderr: stderr.trim().to_string() },
+            false => Self::exit(command, output),
+        }
+    }
+
+    /// Whether this is likely to go away if retried shortly, as when systemd is too busy to answer.
+    pub fn is_transient(&self) -> bool {
+        const TRANSIENT_DBUS_ERRORS: &[&str] = &[
+            "org.freedesktop.DBus.Error.NoReply",
+            "org.freedesktop.DBus.Error.Timeout",
+            "org.freedesktop.DBus.Error.TimedOut",
+            "org.freedesktop.DBus.Error.ServiceUnknown",
+            "org.freedesktop.DBus.Error.Disconnected",
+        ];
+
+        match self {
+            Self::SystemdUnreachable { .. } => true,
+            Self::DbusConnect(source) | Self::Dbus { source, .. } => match source.as_ref() {
+                zbus::Error::InputOutput(_) | zbus::Error::Connection(..) => true,
+                zbus::Error::MethodError(name, _, _) => TRANSIENT_DBUS_ERRORS.contains(&name.as_str()),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Output of `command` that could not be parsed.
+    pub fn parse(command: &str, output: &[u8]) -> Self {
+        Self::Parse { command: command.to_string(), output: String::from_utf8_lossy(output).into_owned() }
+    }
+}
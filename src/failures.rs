@@ -0,0 +1,74 @@
+//! `--quiet-after`, which cuts down on logging restarts that keep failing
+//! the same way to a summary every so often.
+
+use std::time::{Duration, Instant};
+
+/// A restart failure, and how many times in a row restarts have failed the same way.
+struct RepeatedFailure {
+    message: String,
+    count: u32,
+    since: Instant,
+}
+
+/// What to log about a restart failure.
+#[derive(Debug, PartialEq)]
+pub enum Report<'a> {
+    /// The failure itself, as usual
+    Failure(&'a str),
+
+    /// The failure itself, which was the last to be logged before going quiet
+    GoingQuiet(&'a str),
+
+    /// A summary of failing `count` times in a row `over` this long
+    Summary { message: &'a str, count: u32, over: Duration },
+
+    /// Nothing, while quiet
+    Quiet,
+}
+
+/// Restarts that failed the same way in a row, quieted after `quiet_after` of them.
+pub struct RestartFailures {
+    quiet_after: u32,
+    repeated: Option<RepeatedFailure>,
+}
+
+impl RestartFailures {
+    pub fn new(quiet_after: u32) -> Self {
+        Self { quiet_after, repeated: None }
+    }
+
+    /// Count a restart failing at `now` with `message`, returning what to log about it.
+    ///
+    /// Every failure is logged until `quiet_after` in a row were the same,
+    /// after which only every `quiet_after`th is, as a summary. A different
+    /// message starts counting over.
+    pub fn failed(&mut self, message: String, now: Instant) -> Report<'_> {
+        let quiet_after = self.quiet_after;
+
+        if self.repeated.as_ref().is_none_or(|repeated| repeated.message != message) {
+            self.repeated = None;
+        }
+
+        let repeated = self.repeated.get_or_insert(RepeatedFailure { message, count: 0, since: now });
+        repeated.count += 1;
+
+        if repeated.count < quiet_after {
+            Report::Failure(&repeated.message)
+        } else if repeated.count == quiet_after {
+            Report::GoingQuiet(&repeated.message)
+        } else if (repeated.count - quiet_after).is_multiple_of(quiet_after) {
+            let over = Duration::from_secs(now.saturating_duration_since(repeated.since).as_secs());
+            Report::Summary { message: &repeated.message, count: repeated.count, over }
+        } else {
+            Report::Quiet
+        }
+    }
+
+    /// Forget failures once a restart succeeded, returning how many in a row there were if logging had gone quiet.
+    pub fn succeeded(&mut self) -> Option<u32> {
+        self.repeated
+            .take()
+            .map(|repeated| repeated.count)
+            .filter(|&count| count > self.quiet_after)
+    }
+}
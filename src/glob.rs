@@ -0,0 +1,41 @@
+//! Matching of names against simple glob patterns, e.g. `test*`.
+
+/// Whether `s` is a pattern rather than a plain name.
+pub fn is_pattern(s: &str) -> bool {
+    s.contains(['*', '?'])
+}
+
+/// Whether `name` matches `pattern`, in which `*` matches any run of
+/// characters and `?` any single character.
+pub fn matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    // Position just after the last `*` seen, and the position in `name` it is currently matched up to
+    let mut backtrack = None;
+    let (mut p, mut n) = (0, 0);
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, n));
+                p += 1;
+            },
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            },
+            _ => match backtrack {
+                // Let the last `*` swallow one more character and try again
+                Some((after_star, matched_to)) => {
+                    backtrack = Some((after_star, matched_to + 1));
+                    p = after_star;
+                    n = matched_to + 1;
+                },
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
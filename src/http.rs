@@ -0,0 +1,91 @@
+//! Retrying of outbound HTTP requests, shared by everything that makes them,
+//! so that a transient network failure does not lose a notification.
+
+use log::warn;
+use std::hash::{BuildHasher, Hasher};
+use std::{collections, thread, time};
+
+/// Longest wait between two attempts, however many have failed before.
+const MAX_BACKOFF: time::Duration = time::Duration::from_secs(5 * 60);
+
+/// How failed requests are retried, per `--http-retries` and `--http-retry-backoff`.
+#[derive(Clone, Copy)]
+pub struct Retry {
+    pub retries: u32,
+    pub backoff: time::Duration,
+}
+
+/// `url` with all but its scheme, host and port left out, for display.
+///
+/// Webhooks such as Slack's or Discord's carry their secret in the path or
+/// query, so only where a request went is ever logged or printed.
+pub fn redact(url: &str) -> String {
+    let Ok(uri) = url.parse::<ureq::http::Uri>() else { return "[redacted]".to_string() };
+
+    match (uri.scheme_str(), uri.authority()) {
+        (Some(scheme), Some(authority)) => {
+            // Credentials in the URL are secret too
+            let host = authority.as_str().rsplit('@').next().unwrap_or_default();
+
+            match uri.path_and_query().is_some_and(|rest| rest.as_str() != "/") {
+                true => format!("{scheme}://{host}/[redacted]"),
+                false => format!("{scheme}://{host}/"),
+            }
+        },
+        _ => "[redacted]".to_string(),
+    }
+}
+
+/// Whether a request that failed with `error` may succeed if made again.
+///
+/// Client errors other than timeouts and rate limiting are not retried, and
+/// neither are mistakes such as a malformed URL.
+fn is_transient(error: &ureq::Error) -> bool {
+    match error {
+        ureq::Error::StatusCode(status) => matches!(status, 408 | 429 | 500..),
+        ureq::Error::Io(_)
+        | ureq::Error::Timeout(_)
+        | ureq::Error::HostNotFound
+        | ureq::Error::ConnectionFailed
+        | ureq::Error::Protocol(_)
+        | ureq::Error::BodyStalled => true,
+        _ => false,
+    }
+}
+
+/// Somewhere between half of `delay` and all of it, so that monitors failing at once do not retry in lockstep.
+fn jitter(delay: time::Duration) -> time::Duration {
+    // Seeded randomly per instance, which is all the randomness needed here
+    let random = collections::hash_map::RandomState::new().build_hasher().finish();
+    delay / 2 + (delay / 2).mul_f64((random % 1000) as f64 / 1000.0)
+}
+
+impl Retry {
+    /// Make a request to `url` with `attempt`, retrying transient failures.
+    ///
+    /// The wait before each retry doubles from `backoff`, up to a cap, and is
+    /// jittered. This blocks throughout, so it is meant to be called off the
+    /// monitoring loop. Returns the last error along with how many attempts were made.
+    pub fn run(&self, url: &str, mut attempt: impl FnMut() -> Result<(), ureq::Error>) -> Result<(), (ureq::Error, u32)> {
+        let mut delay = self.backoff.min(MAX_BACKOFF);
+        let mut attempts = 0;
+
+        loop {
+            attempts += 1;
+
+            let error = match attempt() {
+                Ok(()) => return Ok(()),
+                Err(error) => error,
+            };
+
+            if attempts > self.retries || !is_transient(&error) {
+                return Err((error, attempts));
+            }
+
+            let wait = jitter(delay);
+            warn!("request to `{}` failed: {error}; retrying in {}ms ({attempts}/{}) ...", redact(url), wait.as_millis(), self.retries);
+            thread::sleep(wait);
+            delay = delay.saturating_mul(2).min(MAX_BACKOFF);
+        }
+    }
+}
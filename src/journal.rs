@@ -0,0 +1,127 @@
+//! Logging to the systemd journal with structured fields, for `--journal`.
+//!
+//! Entries are sent as datagrams in the journal's native protocol, which is
+//! simple enough not to need `libsystemd`: one `FIELD=value` per line, with
+//! values containing newlines length-prefixed instead.
+
+use std::{io, os::unix::net::UnixDatagram, sync, thread};
+
+const SOCKET: &str = "/run/systemd/journal/socket";
+
+const SYSLOG_IDENTIFIER: &str = "wg_restarter";
+
+/// `MESSAGE_ID`s of events by outcome, to find them with e.g. `journalctl MESSAGE_ID=a37ee01f74da4eb08114abd77d4802d4`.
+const MESSAGE_IDS: &[(&str, &str)] = &[
+    ("warning", "0ef4712bbe8c486aaf7a94a7ef17f7f4"),
+    ("restarted", "a37ee01f74da4eb08114abd77d4802d4"),
+    ("restart failed", "7904ef7f1689416dad841fe898ae2ffc"),
+    ("restart unverified", "5b8e2d74c09a4f6e9d31a7c4f2e86b05"),
+    ("rebooting", "52407b3bf65e499194b36ead65d958f1"),
+    ("reboot failed", "024351568a8f4151ac97c7246db34807"),
+    ("breaker open", "dd28f92160d342f3935d638b904d6449"),
+    ("breaker closed", "4941f3307fc946108843724c64c89b18"),
+    ("recovered", "6db1fc2604e64954aa669ba415c50be9"),
+    ("gave up", "c1f05a9e3b7d4e28a6f4d0b7e95c2a13"),
+];
+
+static JOURNAL: sync::OnceLock<UnixDatagram> = sync::OnceLock::new();
+
+/// Connect to the journal, after which `send` sends entries there.
+pub fn connect() -> io::Result<()> {
+    let socket = UnixDatagram::unbound()?;
+    socket.connect(SOCKET)?;
+    let _ = JOURNAL.set(socket);
+    Ok(())
+}
+
+/// Whether `connect` succeeded.
+pub fn is_connected() -> bool {
+    JOURNAL.get().is_some()
+}
+
+/// Encode fields in the native protocol.
+fn encode(fields: &[(&str, &str)]) -> Vec<u8> {
+    let mut entry = Vec::new();
+
+    for (name, value) in fields {
+        entry.extend_from_slice(name.as_bytes());
+
+        if value.contains('\n') {
+            entry.push(b'\n');
+            entry.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        } else {
+            entry.push(b'=');
+        }
+
+        entry.extend_from_slice(value.as_bytes());
+        entry.push(b'\n');
+    }
+
+    entry
+}
+
+/// Send an entry with these fields, plus `SYSLOG_IDENTIFIER`, if connected.
+pub fn send(fields: &[(&str, &str)]) -> io::Result<()> {
+    let Some(socket) = JOURNAL.get() else { return Ok(()) };
+
+    let mut entry = encode(&[("SYSLOG_IDENTIFIER", SYSLOG_IDENTIFIER)]);
+    entry.extend(encode(fields));
+    socket.send(&entry).map(|_| ())
+}
+
+/// The `MESSAGE_ID` of events with this outcome.
+pub fn message_id(outcome: &str) -> Option<&'static str> {
+    MESSAGE_IDS
+        .iter()
+        .find(|&&(name, _)| name == outcome)
+        .map(|&(_, id)| id)
+}
+
+/// Syslog priority of a log level, as the journal's `PRIORITY` field.
+fn priority(level: log::Level) -> &'static str {
+    match level {
+        log::Level::Error => "3",
+        log::Level::Warn => "4",
+        log::Level::Info => "6",
+        log::Level::Debug | log::Level::Trace => "7",
+    }
+}
+
+/// Sends log records to the journal, filtered the same as they would be on stderr.
+///
+/// Records logged from a monitoring thread carry the name of its interface as `WG_INTERFACE`.
+pub struct Logger {
+    filter: env_logger::Logger,
+}
+
+impl Logger {
+    pub fn new(filter: env_logger::Logger) -> Self {
+        Self { filter }
+    }
+}
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.filter.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.filter.matches(record) {
+            return;
+        }
+
+        let message = record.args().to_string();
+        let mut fields = vec![("MESSAGE", message.as_str()), ("PRIORITY", priority(record.level()))];
+
+        let thread = thread::current();
+
+        if let Some(interface) = thread.name().filter(|&name| name != "main") {
+            fields.push(("WG_INTERFACE", interface));
+        }
+
+        // There is nowhere left to report a failure to
+        let _ = send(&fields);
+    }
+
+    fn flush(&self) {}
+}
@@ -0,0 +1,14 @@
+//! Internals of wg_restarter, split out of the binary so they can be benchmarked and tested.
+
+pub mod clock;
+pub mod cooldown;
+pub mod duration;
+pub mod endpoints;
+pub mod failures;
+pub mod glob;
+pub mod rate_limit;
+pub mod signal;
+pub mod size;
+pub mod threshold;
+pub mod wg;
+pub mod wg_quick;
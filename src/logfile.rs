@@ -0,0 +1,100 @@
+//! `--log-file`, a log file that rotates itself once it grows past
+//! `--log-max-size`, keeping `--log-keep` older files around as `<path>.1`,
+//! `<path>.2` and so on.
+
+use crate::error::Error;
+use std::io::Write;
+use std::{fs, io, path};
+
+/// Where log records are currently being written.
+enum Sink {
+    File(fs::File),
+
+    /// Rotation failed, so everything goes to stderr from then on
+    Stderr,
+}
+
+pub struct RotatingFile {
+    path: path::PathBuf,
+    max_size: u64,
+    keep: u32,
+    size: u64,
+    sink: Sink,
+}
+
+fn open_append(path: &path::Path) -> io::Result<fs::File> {
+    fs::OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// Path of the `n`th older file, e.g. `wg_restarter.log.2`.
+fn rotated(path: &path::Path, n: u32) -> path::PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{n}"));
+    name.into()
+}
+
+impl RotatingFile {
+    /// Open `path` for appending, creating it if need be.
+    pub fn open(path: &path::Path, max_size: u64, keep: u32) -> Result<Self, Error> {
+        let file = open_append(path).map_err(|source| Error::Write { path: path.to_path_buf(), source })?;
+        let size = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_size,
+            keep,
+            size,
+            sink: Sink::File(file),
+        })
+    }
+
+    /// Shift older files up by one, dropping the oldest, and start a new file.
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            for n in (1..self.keep).rev() {
+                match fs::rename(rotated(&self.path, n), rotated(&self.path, n + 1)) {
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                    _ => {},
+                }
+            }
+
+            fs::rename(&self.path, rotated(&self.path, 1))?;
+        }
+
+        self.sink = Sink::File(open_append(&self.path)?);
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if matches!(self.sink, Sink::File(_))
+            && self.size > 0
+            && self.size + buf.len() as u64 > self.max_size
+            && let Err(e) = self.rotate()
+        {
+            // Logging about the logger would only land back here
+            eprintln!("failed to rotate `{}`: {e}; logging to stderr instead ...", self.path.display());
+            self.sink = Sink::Stderr;
+        }
+
+        match &mut self.sink {
+            Sink::File(file) => {
+                let written = file.write(buf)?;
+                self.size += written as u64;
+                Ok(written)
+            },
+            Sink::Stderr => io::stderr().write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.sink {
+            Sink::File(file) => file.flush(),
+            Sink::Stderr => io::stderr().flush(),
+        }
+    }
+}
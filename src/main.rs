@@ -1,161 +1,3568 @@
-use clap::Parser;
-use humantime;
-use std::{env, thread, time, process};
+mod aggregate;
+mod credential;
+mod dbus;
+mod doctor;
+mod driver;
+mod dump;
+mod error;
+mod http;
+mod journal;
+mod logfile;
+mod metrics;
+mod notify;
+mod output;
+mod replay;
+mod runner;
+mod sd_notify;
+mod secret;
+mod show;
+mod state;
+mod status;
+mod webhook;
 
-const VERSION: &'static str = concat!("v", env!("CARGO_PKG_VERSION"), "-alpha.01");
-const ABOUT: &'static str = "wireguard interface restarter\n$ git clone https://github.com/zorael/wg_restarter";
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use aggregate::Aggregator;
+use error::Error;
+use log::{debug, error, info, warn};
+use output::OutputFormat;
+use std::{collections, env, io::{self, Read, Write}, path, sync, thread, time, process};
+use runner::CommandRunner;
+use secret::Secret;
+use webhook::Webhook;
+use wg_restarter::{clock, duration, failures, glob, rate_limit, signal, size, threshold, wg, wg_quick};
+use wg_restarter::cooldown::NotifyCooldown;
+use wg_restarter::endpoints::EndpointHistory;
+use wg_restarter::rate_limit::RestartHistory;
+use wg_restarter::signal::Signal;
+use wg_restarter::threshold::Threshold;
+
+const VERSION: &str = concat!("v", env!("CARGO_PKG_VERSION"), "-alpha.01");
+const ABOUT: &str = "wireguard interface restarter\n$ git clone https://github.com/zorael/wg_restarter";
+const AFTER_HELP: &str = "DURATION values are a number of seconds (`600`) or a duration with units (`600s`, `10m`, `1h 30m`).";
+
+/// How the WireGuard connection is managed.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Backend {
+    /// `wg-quick@` systemd service, checked and restarted with `systemctl`
+    Systemd,
+
+    /// `wg-quick@` systemd service, checked and restarted by talking to systemd over D-Bus; local only
+    Dbus,
+
+    /// Arbitrary frontend, checked and restarted with user-provided commands
+    Script,
+}
+
+/// How multiple handshake samples are combined into one reading.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SampleAggregate {
+    /// Median handshake age
+    Median,
+
+    /// Oldest handshake age
+    Worst,
+}
+
+/// What to do when the unit is found cleanly stopped, as opposed to failed.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OnUnitStopped {
+    /// Exit successfully, leaving the unit stopped
+    Exit,
+
+    /// Stop monitoring until the unit is started again
+    Pause,
+
+    /// Restart the unit, as if it had failed
+    Restart,
+}
+
+/// What to do once the `--peer` has been missing from the interface for a while.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OnPeerAbsent {
+    /// Keep looking for the peer, warning loudly that its key may have changed
+    Warn,
+
+    /// Monitor the interface as if `--peer` was not given, until the peer reappears
+    Fallback,
+}
+
+/// What to do when the interface has no peers configured at all.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OnNoPeers {
+    /// Exit with a failure
+    Exit,
+
+    /// Stop monitoring until peers are configured
+    Pause,
+
+    /// Keep monitoring, as if no handshake had been recorded (or no bytes received)
+    Wait,
+}
+
+/// What to do when the latest handshake is in the future, i.e. the clock jumped backwards.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OnClockAnomaly {
+    /// Log a warning and treat the handshake as having just happened
+    Warn,
+
+    /// Restart, as if the handshake had timed out
+    Restart,
+
+    /// Skip the check until the clock catches up
+    Skip,
+}
+
+/// How a reading compares to the timeout, as judged by [`Cli::staleness`].
+enum Staleness {
+    /// Within `--timeout`
+    Fresh,
+
+    /// Past `--timeout`, but within `--overshoot-deadband`, which is this long
+    InDeadband(time::Duration),
+
+    /// Past both
+    Stale,
+}
+
+/// A nonzero handshake timestamp, judged against the local clock by [`OnClockAnomaly::age`].
+enum HandshakeAge {
+    /// How long ago the handshake was, or zero if it is in the future and only to be warned about
+    Age(time::Duration),
+
+    /// The handshake is in the future and is to be treated as timed out
+    Restart,
+
+    /// The handshake is in the future and the check is to be skipped
+    Skip,
+}
+
+impl OnClockAnomaly {
+    /// How old the handshake at `timestamp` is as of `now`, applying this to one in the future.
+    ///
+    /// This is the only place clock anomalies are handled, for the monitoring
+    /// loop and `--stdin-driver` alike. `clock::age` tells a future handshake
+    /// apart from one that just happened, rather than passing it off as zero.
+    /// `what` names the handshake in what gets logged.
+    fn age(self, now: time::SystemTime, timestamp: u64, what: &str) -> HandshakeAge {
+        let ahead = match clock::age(now, timestamp) {
+            Ok(elapsed) => return HandshakeAge::Age(elapsed),
+            Err(ahead) => ahead.as_secs(),
+        };
+
+        match self {
+            Self::Warn => {
+                warn!("{what} is {ahead}s in the future; the clock may have jumped backwards ...");
+                HandshakeAge::Age(time::Duration::ZERO)
+            },
+            Self::Restart => {
+                warn!("{what} is {ahead}s in the future; the clock may have jumped backwards. treating it as timed out ...");
+                HandshakeAge::Restart
+            },
+            Self::Skip => {
+                info!("{what} is {ahead}s in the future; skipping check until the clock catches up ...");
+                HandshakeAge::Skip
+            },
+        }
+    }
+}
+
+/// What to do once the circuit breaker has been tried at `--breaker-max-cooldown` and restarts still do not help.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum GiveupAction {
+    /// Stop restarting for good, and carry on monitoring until the connection recovers on its own
+    Idle,
+
+    /// Exit with `GIVEUP_EXIT_CODE`, so that a supervisor can take over
+    Exit,
+}
+
+/// Exit code when giving up with `--giveup-action exit`, distinct from 1 for errors and 2 for invalid arguments.
+const GIVEUP_EXIT_CODE: u8 = 3;
+
+/// A named bundle of timing defaults, for `--profile`.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Profile {
+    /// The defaults of the individual options
+    Default,
+
+    /// Long timeout and patient retries, for links that come and go, such as on a phone or laptop
+    Mobile,
+
+    /// Tight timeout and fast retries, for stable links where any gap is worth acting on
+    Datacenter,
+}
+
+/// Timing settings a profile sets, unless given explicitly.
+struct ProfileSettings {
+    timeout: time::Duration,
+    loop_interval: time::Duration,
+    retry_after_unit_restart: time::Duration,
+    restart_in_flight: time::Duration,
+}
+
+/// What each profile expands to; `Default` must match the `default_value`s of the options.
+const PROFILES: &[(Profile, ProfileSettings)] = &[
+    (Profile::Default, ProfileSettings {
+        timeout: time::Duration::from_secs(10 * 60),
+        loop_interval: time::Duration::from_secs(60),
+        retry_after_unit_restart: time::Duration::from_secs(30),
+        restart_in_flight: time::Duration::from_secs(60),
+    }),
+    (Profile::Mobile, ProfileSettings {
+        timeout: time::Duration::from_secs(30 * 60),
+        loop_interval: time::Duration::from_secs(2 * 60),
+        retry_after_unit_restart: time::Duration::from_secs(2 * 60),
+        restart_in_flight: time::Duration::from_secs(5 * 60),
+    }),
+    (Profile::Datacenter, ProfileSettings {
+        timeout: time::Duration::from_secs(3 * 60),
+        loop_interval: time::Duration::from_secs(15),
+        retry_after_unit_restart: time::Duration::from_secs(10),
+        restart_in_flight: time::Duration::from_secs(30),
+    }),
+];
+
+/// How `systemctl` is told to bounce the unit.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SystemctlVerb {
+    /// Stop the unit and start it again, starting it if it is not running
+    Restart,
+
+    /// Restart the unit only if it is running
+    TryRestart,
+
+    /// Reload the unit if it supports reloading, otherwise restart it
+    ReloadOrRestart,
+
+    /// Reload the unit's configuration, failing if it does not support reloading
+    Reload,
+}
+
+impl SystemctlVerb {
+    /// The `systemctl` command.
+    fn command(self) -> &'static str {
+        match self {
+            Self::Restart => "restart",
+            Self::TryRestart => "try-restart",
+            Self::ReloadOrRestart => "reload-or-restart",
+            Self::Reload => "reload",
+        }
+    }
+
+    /// The method of systemd's manager doing the same over D-Bus.
+    fn dbus_method(self) -> &'static str {
+        match self {
+            Self::Restart => "RestartUnit",
+            Self::TryRestart => "TryRestartUnit",
+            Self::ReloadOrRestart => "ReloadOrRestartUnit",
+            Self::Reload => "ReloadUnit",
+        }
+    }
+}
+
+/// What signal of tunnel health is monitored.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Mode {
+    /// Restart when the latest handshake is older than the timeout
+    Handshake,
+
+    /// Restart when no bytes have been received for the timeout
+    RxProgress,
+}
+
+/// How peer public keys are shown in logs and output.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum PeerKeyDisplay {
+    /// The whole key
+    Full,
+
+    /// The first 8 characters of the key
+    Truncated,
+
+    /// Nothing of the key
+    Redacted,
+}
 
 #[derive(Parser)]
 #[command(name = "wg_restarter")]
 #[command(author = "jr <zorael@protonmail.com>")]
 #[command(version = VERSION)]
 #[command(about = ABOUT)]
+#[command(after_help = AFTER_HELP)]
 #[command(arg_required_else_help = true)]
+#[command(disable_help_subcommand = true)]
+#[command(args_override_self = true)]
+#[command(group(clap::ArgGroup::new("peer_selection").args(["peer", "peer_name"])))]
 struct Cli {
-    /// Handshake timeout in seconds
-    #[arg(short = 't', long, value_parser = humantime::parse_duration, default_value = "10m")]
+    /// Read further options from the systemd credential of this name, `$CREDENTIALS_DIRECTORY/NAME`, as `option = value` lines; the command line takes precedence
+    #[arg(long, value_name = "NAME")]
+    credential: Option<String>,
+
+    /// Preset for the timeout, loop interval, retry interval and `--restart-in-flight`, each of which still overrides it when given
+    #[arg(long, value_enum, default_value_t = Profile::Default)]
+    profile: Profile,
+
+    /// Handshake timeout
+    #[arg(short = 't', long, value_name = "DURATION", value_parser = duration::parse, default_value = "10m")]
     timeout: time::Duration,
 
-    /// Loop interval in seconds
-    #[arg(short = 'l', long, value_parser = humantime::parse_duration, default_value = "60s")]
-    loop_interval: time::Duration,
+    /// Restart once the latest handshake is older than this, regardless of maintenance, grace periods and the circuit breaker
+    #[arg(long, alias = "max-handshake-age-ever", value_name = "DURATION", value_parser = duration::parse)]
+    hard_timeout: Option<time::Duration>,
+
+    /// Loop interval
+    #[arg(short = 'l', long, value_name = "DURATION", value_parser = duration::parse, default_value = "60s")]
+    loop_interval: time::Duration,
+
+    /// Retry interval after unit restart
+    #[arg(short = 'r', long, value_name = "DURATION", value_parser = duration::parse, default_value = "30s")]
+    retry_after_unit_restart: time::Duration,
+
+    /// After a restart, poll for a fresh handshake instead of waiting out the retry interval, and check again at once if none appears
+    #[arg(long)]
+    verify_after_restart: bool,
+
+    /// Retry after a restart after this many times the peers' persistent keepalive instead, if one is configured
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    retry_keepalive_multiple: Option<u32>,
+
+    /// For this long after a successful restart, until it takes visible effect, do not restart again; `0` to disable
+    #[arg(long, value_name = "DURATION", value_parser = duration::parse, default_value = "1m")]
+    restart_in_flight: time::Duration,
+
+    /// What signal of tunnel health is monitored
+    #[arg(short = 'm', long, value_enum, default_value_t = Mode::Handshake)]
+    mode: Mode,
+
+    /// Warn, without restarting, when staleness crosses this duration or percentage of the timeout, e.g. `7m` or `70%`
+    #[arg(long, value_name = "DURATION|PERCENT", value_parser = parse_warn_threshold)]
+    warn_threshold: Option<Threshold>,
+
+    /// Only restart once staleness exceeds the timeout by more than this duration or percentage of it, e.g. `30s` or `5%`
+    #[arg(long, value_name = "DURATION|PERCENT", value_parser = parse_overshoot_deadband)]
+    overshoot_deadband: Option<Threshold>,
+
+    /// After being stale, only consider the connection recovered once staleness is this far below the timeout
+    #[arg(long, value_name = "DURATION", value_parser = duration::parse)]
+    handshake_freshness_hysteresis: Option<time::Duration>,
+
+    /// Also restart when a peer's endpoint keeps changing without a handshake, as with NAT rebinding
+    #[arg(long)]
+    endpoint_change_detect: bool,
+
+    /// Endpoint changes within `--endpoint-change-window` tolerated before `--endpoint-change-detect` restarts
+    #[arg(long, value_name = "N", default_value_t = 3, requires = "endpoint_change_detect")]
+    max_endpoint_changes: u32,
+
+    /// Window endpoint changes are counted within, for `--endpoint-change-detect`
+    #[arg(long, value_name = "DURATION", value_parser = duration::parse, default_value = "10m", requires = "endpoint_change_detect")]
+    endpoint_change_window: time::Duration,
+
+    /// Restart if no handshake has been recorded at all within this duration
+    #[arg(short = 'f', long, value_name = "DURATION", value_parser = duration::parse)]
+    first_handshake_timeout: Option<time::Duration>,
+
+    /// Log at warning level once there has been no handshake for this long; defaults to half of `--first-handshake-timeout`
+    #[arg(long, value_name = "DURATION", value_parser = duration::parse)]
+    on_first_start_wait: Option<time::Duration>,
+
+    /// Monitor only this peer, by public key, instead of the first peer (or all peers, with `--mode rx-progress`)
+    #[arg(long, value_name = "PUBKEY")]
+    peer: Option<String>,
+
+    /// Monitor only the peer with this name, as given by a `# Name = ...` comment in the interface's `wg-quick` config
+    #[arg(long, value_name = "NAME")]
+    peer_name: Option<String>,
+
+    /// `wg-quick` config to look up `--peer-name` in, if not `/etc/wireguard/<interface>.conf`
+    #[arg(long, value_name = "FILE", requires = "peer_name")]
+    wg_config: Option<path::PathBuf>,
+
+    /// Only consider the connection healthy if at least this many peers have fresh handshakes; overridden by `--peer`
+    #[arg(long, value_name = "K", value_parser = clap::value_parser!(u32).range(1..))]
+    min_fresh_peers: Option<u32>,
+
+    /// Number of consecutive checks `--peer` may be missing from the interface before it is reported as likely gone
+    #[arg(long, value_name = "N", default_value_t = 3, value_parser = clap::value_parser!(u32).range(1..), requires = "peer_selection")]
+    peer_absent_threshold: u32,
+
+    /// What to do once `--peer` has been missing for `--peer-absent-threshold` checks
+    #[arg(long, value_enum, default_value_t = OnPeerAbsent::Warn, requires = "peer_selection")]
+    on_peer_absent: OnPeerAbsent,
+
+    /// What to do when the interface has no peers configured
+    #[arg(long, value_enum, default_value_t = OnNoPeers::Wait)]
+    on_no_peers: OnNoPeers,
+
+    /// What to do when the latest handshake is in the future, as after the clock jumped backwards
+    #[arg(long, value_enum, default_value_t = OnClockAnomaly::Warn)]
+    on_clock_anomaly: OnClockAnomaly,
+
+    /// Systemd unit to check and restart, if not `wg-quick@<interface>.service`
+    #[arg(long, value_name = "UNIT")]
+    unit: Option<String>,
+
+    /// Interface to monitor handshakes on; same as the positional INTERFACE, for symmetry with `--unit`
+    #[arg(long, value_name = "INTERFACE", conflicts_with = "interface")]
+    probe_interface: Option<String>,
+
+    /// Monitor every interface listed by `wg show interfaces`, each with its `wg-quick@` unit
+    #[arg(long, conflicts_with_all = ["interface", "probe_interface", "unit", "peer_selection"])]
+    all: bool,
+
+    /// How often `--all` or an interface pattern looks for interfaces that appeared or went away
+    #[arg(long, value_name = "DURATION", value_parser = duration::parse, default_value = "1m")]
+    rescan_interval: time::Duration,
+
+    /// Leave interfaces matching this name or glob pattern, e.g. `test*`, out of `--all` or an interface pattern; may be repeated
+    #[arg(long, value_name = "PATTERN")]
+    ignore_interface: Vec<String>,
+
+    /// How the WireGuard connection is managed
+    #[arg(short = 'b', long, value_enum, default_value_t = Backend::Systemd)]
+    backend: Backend,
+
+    /// For this long after the unit (re)starts, measure handshake staleness from the unit start rather than the last handshake
+    #[arg(long, value_name = "DURATION", value_parser = duration::parse)]
+    unit_start_window: Option<time::Duration>,
+
+    /// On the first check, do not wait out `--unit-start-window` or `--first-handshake-timeout` for a tunnel that is already stale
+    #[arg(long)]
+    restart_on_startup_if_stale: bool,
+
+    /// Never restart within this long after the system booted, while networking may still be settling
+    #[arg(long, value_name = "DURATION", value_parser = duration::parse)]
+    post_boot_window: Option<time::Duration>,
+
+    /// Never restart or reboot until the system has been up this long, even past `--hard-timeout`, so a host that keeps crashing is left alone
+    #[arg(long, value_name = "DURATION", value_parser = duration::parse)]
+    min_uptime_before_acting: Option<time::Duration>,
+
+    /// Write Prometheus metrics to this file after every check and on exit, for the node_exporter textfile collector
+    #[arg(long, value_name = "PATH")]
+    metrics_textfile: Option<path::PathBuf>,
+
+    /// Keep restarts without recovery, the circuit breaker and the last fresh handshake in this file, to carry on with them after the monitor restarts
+    #[arg(long, value_name = "PATH")]
+    state_file: Option<path::PathBuf>,
+
+    /// Run all commands except `--notify-command` and the pause file hooks on this remote host over SSH, e.g. `root@router`
+    #[arg(long, value_name = "DESTINATION")]
+    ssh: Option<String>,
+
+    /// What to do when the systemd unit is stopped deliberately (`inactive` rather than `failed`)
+    #[arg(long, value_enum, default_value_t = OnUnitStopped::Pause)]
+    on_unit_stopped: OnUnitStopped,
+
+    /// Pause monitoring and restarts for as long as this file exists, e.g. during maintenance
+    #[arg(long, value_name = "FILE")]
+    pause_file: Option<path::PathBuf>,
+
+    /// Shell command run when `--pause-file` appears
+    #[arg(long, requires = "pause_file")]
+    on_pause_command: Option<String>,
+
+    /// Shell command run when `--pause-file` is removed
+    #[arg(long, requires = "pause_file")]
+    on_resume_command: Option<String>,
+
+    /// Shell command run at startup and on every check to see whether the connection is up; exit status 0 means up
+    #[arg(long, required_if_eq("backend", "script"))]
+    up_check_command: Option<String>,
+
+    /// Give up on a restart that has not finished after this long, killing the command, and count it as failed
+    #[arg(long, value_name = "DURATION", value_parser = duration::parse)]
+    restart_timeout: Option<time::Duration>,
+
+    /// Command to run instead of restarting the unit
+    #[arg(long, required_if_eq("backend", "script"))]
+    restart_command: Option<String>,
+
+    /// What `systemctl` is told to do to the unit to remediate, instead of `restart`
+    #[arg(long, value_enum, value_name = "VERB", default_value_t = SystemctlVerb::Restart, conflicts_with_all = ["restart_command", "restart_signal"])]
+    systemctl_verb: SystemctlVerb,
+
+    /// Only queue the restart with `systemctl --no-block restart` instead of waiting for the unit to come back up
+    #[arg(long, conflicts_with = "restart_command")]
+    restart_no_block: bool,
+
+    /// Send this signal to the main process of the unit instead of restarting it, e.g. `HUP` for units that reload on it; restarts if sending it fails
+    #[arg(long, value_name = "SIGNAL", value_parser = signal::parse, conflicts_with = "restart_command")]
+    restart_signal: Option<Signal>,
+
+    /// After this many restarts in a row failing the same way, only log every as many further identical failures, as a summary
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    quiet_after: Option<u32>,
+
+    /// Maximum number of restarts within a rolling time window, e.g. `5/1h`
+    #[arg(long, value_name = "COUNT/WINDOW", value_parser = rate_limit::parse)]
+    max_restarts_per: Option<rate_limit::RateLimit>,
+
+    /// Where latest-handshakes output is read from: `wg`, or `file:PATH` to read it from a file instead, or `replay:PATH` to step through handshake ages, for testing
+    #[arg(long, value_name = "SOURCE", value_parser = parse_handshake_source, default_value = "wg")]
+    handshake_source: HandshakeSource,
+
+    /// Tab-separated column of the latest-handshakes output holding the timestamp, counting the public key as 0
+    #[arg(long, value_name = "N", default_value_t = wg::HANDSHAKE_COLUMN, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    handshake_column: usize,
+
+    /// Number of handshake readings taken per loop
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    sample_count: u32,
+
+    /// Delay between handshake readings within one loop
+    #[arg(long, value_name = "DURATION", value_parser = duration::parse, default_value = "1s")]
+    sample_spacing: time::Duration,
+
+    /// How handshake readings within one loop are combined
+    #[arg(long, value_enum, default_value_t = SampleAggregate::Median)]
+    sample_aggregate: SampleAggregate,
+
+    /// Write a diagnostic snapshot of the interface to this directory before each restart
+    #[arg(long, value_name = "DIR")]
+    dump_on_timeout: Option<path::PathBuf>,
+
+    /// Number of diagnostic snapshots to keep per interface
+    #[arg(long, value_name = "N", default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..), requires = "dump_on_timeout")]
+    dump_keep: u32,
+
+    /// Shell command run after each restart or reboot and on crossing `--warn-threshold`, with the notification message as `$1`
+    #[arg(long)]
+    notify_command: Option<String>,
+
+    /// URL notifications are posted to as JSON, in addition to or instead of `--notify-command`; may be repeated or comma-separated
+    #[arg(long, value_name = "URL", value_delimiter = ',')]
+    notify_webhook: Vec<String>,
+
+    /// With `--all` or an interface pattern, hold restart notifications back for this long and send those of all interfaces as one
+    #[arg(long, value_name = "DURATION", value_parser = duration::parse)]
+    aggregate_window: Option<time::Duration>,
+
+    /// Give up on posting to a `--notify-webhook` after this long
+    #[arg(long, value_name = "DURATION", value_parser = duration::parse, default_value = "10s", requires = "notify_webhook")]
+    webhook_timeout: time::Duration,
+
+    /// Retry a failed HTTP request, such as to a `--notify-webhook`, this many times
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    http_retries: u32,
+
+    /// Wait about this long before the first retry of a failed HTTP request, doubling with every retry after it
+    #[arg(long, value_name = "DURATION", value_parser = duration::parse, default_value = "1s")]
+    http_retry_backoff: time::Duration,
+
+    /// File containing a bearer token for `--notify-webhook`; otherwise read from `WG_RESTARTER_WEBHOOK_TOKEN`, if set
+    #[arg(long, value_name = "FILE", requires = "notify_webhook")]
+    webhook_token_file: Option<path::PathBuf>,
+
+    /// Before restarting, wait for the tunnel to stop carrying traffic, for up to `--defer-window`
+    #[arg(long)]
+    defer_if_active: bool,
+
+    /// Longest a restart is deferred for while the tunnel carries traffic, for `--defer-if-active`
+    #[arg(long, value_name = "DURATION", value_parser = duration::parse, default_value = "5m", requires = "defer_if_active")]
+    defer_window: time::Duration,
+
+    /// Do not notify about the same event again within this duration, and summarize what was suppressed once it has passed
+    #[arg(long, value_name = "DURATION", value_parser = duration::parse)]
+    notify_cooldown: Option<time::Duration>,
+
+    /// Also notify when the connection recovers after having been stale
+    #[arg(long)]
+    notify_recovery: bool,
+
+    /// Notification message; placeholders are {interface}, {unit}, {elapsed}, {timeout}, {outcome} and {timestamp}
+    #[arg(long, value_name = "TEMPLATE", value_parser = notify::Template::parse, default_value = notify::DEFAULT_TEMPLATE)]
+    notify_template: notify::Template,
+
+    /// Reboot the system after this many consecutive restarts without recovery (off by default)
+    #[arg(long, value_name = "COUNT", value_parser = clap::value_parser!(u32).range(1..))]
+    reboot_after: Option<u32>,
+
+    /// Shell command run to reboot the system
+    #[arg(long, default_value = "systemctl reboot", requires = "reboot_after")]
+    reboot_command: String,
+
+    /// Never reboot unless the system has been up at least this long, to avoid boot loops
+    #[arg(long, value_name = "DURATION", value_parser = duration::parse, default_value = "1h", requires = "reboot_after")]
+    reboot_min_uptime: time::Duration,
+
+    /// Stop restarting and alert after this many consecutive restarts without recovery, until a cooldown passes (off by default)
+    #[arg(long, value_name = "COUNT", value_parser = clap::value_parser!(u32).range(1..))]
+    breaker_after: Option<u32>,
+
+    /// How long to stop restarting for before one trial restart; doubled each time the trial does not recover
+    #[arg(long, value_name = "DURATION", value_parser = duration::parse, default_value = "30m", requires = "breaker_after")]
+    breaker_cooldown: time::Duration,
+
+    /// Longest the cooldown is doubled up to
+    #[arg(long, value_name = "DURATION", value_parser = duration::parse, default_value = "8h", requires = "breaker_after")]
+    breaker_max_cooldown: time::Duration,
+
+    /// What to do when a trial restart fails at `--breaker-max-cooldown`; otherwise trial restarts carry on at that cooldown
+    #[arg(long, value_enum, requires = "breaker_after")]
+    giveup_action: Option<GiveupAction>,
+
+    /// Do not warn at startup when running as a user that is likely not allowed to restart the unit
+    #[arg(long)]
+    skip_privilege_check: bool,
+
+    /// Exit with a failure instead of skipping the loop iteration when a command fails or prints unexpected output
+    #[arg(long)]
+    fail_fast: bool,
+
+    /// Read `interface<TAB>handshake_ts` lines from stdin and write `restart <interface>` or `ok <interface>` for each, without running any commands
+    #[arg(long, conflicts_with_all = [
+        "interface", "all", "ssh", "list_interfaces", "mode", "hard_timeout", "endpoint_change_detect", "peer", "peer_name",
+        "min_fresh_peers", "unit_start_window", "restart_on_startup_if_stale", "post_boot_window", "min_uptime_before_acting",
+        "max_restarts_per", "defer_if_active", "reboot_after", "breaker_after",
+    ])]
+    stdin_driver: bool,
+
+    /// For testing only: treat the first loop iteration, or with `=N` every Nth, as a timeout and restart
+    #[arg(long, value_name = "N", num_args = 0..=1, require_equals = true, value_parser = clap::value_parser!(u32).range(1..), hide_short_help = true)]
+    simulate_timeout: Option<Option<u32>>,
+
+    /// Validate the configuration and the interface, unit and commands it refers to, print a report and exit
+    #[arg(long, conflicts_with_all = ["list_interfaces", "print_config", "stdin_driver"])]
+    check_config: bool,
+
+    /// List the WireGuard interfaces on the host, with their state, and exit
+    #[arg(long)]
+    list_interfaces: bool,
+
+    /// Print the effective configuration and exit
+    #[arg(long)]
+    print_config: bool,
+
+    /// Print one JSON line with the effective configuration and the interfaces about to be monitored, then monitor as usual
+    #[arg(long, conflicts_with_all = ["print_config", "check_config", "list_interfaces"])]
+    startup_json: bool,
+
+    /// Format of `--list-interfaces`, `--print-config`, the `doctor` report and the summary printed on exit
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    output_format: OutputFormat,
+
+    /// How peer public keys are shown in logs, notifications and `--print-config`
+    #[arg(long, value_enum, value_name = "DISPLAY", default_value_t = PeerKeyDisplay::Truncated)]
+    log_peer_key: PeerKeyDisplay,
+
+    /// Log level; overrides the default level of `RUST_LOG`, which is otherwise `info`
+    #[arg(long, value_name = "LEVEL")]
+    log_level: Option<log::LevelFilter>,
+
+    /// Log to the systemd journal with structured fields instead of to stderr, if the journal is available
+    #[arg(long)]
+    journal: bool,
+
+    /// Log to this file instead of to stderr, rotating it once it grows past `--log-max-size`
+    #[arg(long, value_name = "PATH", conflicts_with = "journal")]
+    log_file: Option<path::PathBuf>,
+
+    /// Size `--log-file` is rotated at, in bytes or with a `K`, `M` or `G` suffix
+    #[arg(long, value_name = "SIZE", value_parser = size::parse, default_value = "10M", requires = "log_file")]
+    log_max_size: u64,
+
+    /// Number of rotated log files kept next to `--log-file`, as `<path>.1` and onwards
+    #[arg(long, value_name = "N", default_value_t = 5, requires = "log_file")]
+    log_keep: u32,
+
+    /// Verbose output; shorthand for `--log-level debug`
+    #[arg(short = 'v', long, conflicts_with = "log_level")]
+    verbose: bool,
+
+    /// WireGuard interface to monitor, or a pattern like `wg*` to monitor every interface matching it
+    interface: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// Things to do instead of monitoring.
+#[derive(Subcommand)]
+enum Command {
+    /// Check that everything needed to monitor an interface is in place, and exit; options go before `doctor`
+    Doctor {
+        /// WireGuard interface to check
+        interface: String,
+    },
+
+    /// Read the handshake once and print a one-line summary, without ever restarting; options go before `status`
+    Status {
+        /// WireGuard interface to report on
+        interface: String,
+
+        /// Line printed; placeholders are {interface}, {symbol}, {age} and {state}
+        #[arg(long, value_name = "FORMAT", value_parser = status::parse_format, default_value = status::DEFAULT_FORMAT)]
+        format: notify::Template,
+    },
+
+    /// Read every peer of an interface once and print its status, without ever restarting; options go before `show`
+    Show {
+        /// WireGuard interface to report on
+        interface: String,
+
+        /// Print a JSON array, as with `--output-format json`
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+impl Cli {
+    /// Fill in the settings of `--profile` that were left at their defaults.
+    fn apply_profile(&mut self, matches: &clap::ArgMatches) {
+        let (_, settings) = PROFILES
+            .iter()
+            .find(|(profile, _)| *profile == self.profile)
+            .expect("every profile has settings");
+
+        let defaulted = |id| matches.value_source(id) == Some(clap::parser::ValueSource::DefaultValue);
+
+        if defaulted("timeout") {
+            self.timeout = settings.timeout;
+        }
+
+        if defaulted("loop_interval") {
+            self.loop_interval = settings.loop_interval;
+        }
+
+        if defaulted("retry_after_unit_restart") {
+            self.retry_after_unit_restart = settings.retry_after_unit_restart;
+        }
+
+        if defaulted("restart_in_flight") {
+            self.restart_in_flight = settings.restart_in_flight;
+        }
+    }
+
+    /// The interface to monitor, given either positionally or with `--probe-interface`.
+    fn interface(&self) -> Option<&str> {
+        self.interface.as_deref().or(self.probe_interface.as_deref())
+    }
+
+    /// How stale a reading `elapsed` old is by `--timeout` and `--overshoot-deadband` alone.
+    ///
+    /// This is the part of deciding on staleness that needs nothing but the
+    /// age itself, shared by the monitoring loop and `--stdin-driver`.
+    fn staleness(&self, elapsed: time::Duration) -> Staleness {
+        if elapsed <= self.timeout {
+            return Staleness::Fresh;
+        }
+
+        match self.overshoot_deadband.map(|d| d.resolve(self.timeout)) {
+            Some(deadband) if elapsed <= self.timeout + deadband => Staleness::InDeadband(deadband),
+            _ => Staleness::Stale,
+        }
+    }
+
+    /// A peer public key as `--log-peer-key` says to show it.
+    fn peer_key(&self, public_key: &str) -> String {
+        match self.log_peer_key {
+            PeerKeyDisplay::Full => public_key.to_string(),
+            PeerKeyDisplay::Truncated => format!("{}...", public_key.get(..8).unwrap_or(public_key)),
+            PeerKeyDisplay::Redacted => "(redacted)".to_string(),
+        }
+    }
+
+    /// The interface given, if it is a pattern matching any number of interfaces.
+    fn interface_pattern(&self) -> Option<&str> {
+        self.interface.as_deref().filter(|interface| glob::is_pattern(interface))
+    }
+
+    /// How long the first handshake is waited for before that is logged as a warning, if ever.
+    fn first_start_wait(&self) -> Option<time::Duration> {
+        self.on_first_start_wait.or(self.first_handshake_timeout.map(|timeout| timeout / 2))
+    }
+
+    /// The systemd unit to check and restart for `interface`.
+    fn unit_name(&self, interface: &str) -> String {
+        match &self.unit {
+            Some(unit) => unit.clone(),
+            None => format!("wg-quick@{interface}.service"),
+        }
+    }
+}
+
+/// Parse a warn threshold, which must be below the timeout.
+fn parse_warn_threshold(s: &str) -> Result<Threshold, String> {
+    threshold::parse(s, 1..=99)
+}
+
+/// Parse an overshoot deadband, which may be as long as the timeout itself.
+fn parse_overshoot_deadband(s: &str) -> Result<Threshold, String> {
+    threshold::parse(s, 1..=100)
+}
+
+/// Where latest-handshakes output comes from.
+#[derive(Clone)]
+enum HandshakeSource {
+    /// `wg show <interface> latest-handshakes`
+    Wg,
+
+    /// A file in the same format, so staleness can be simulated by editing it
+    File(path::PathBuf),
+
+    /// `wg show all latest-handshakes`, shared between interfaces for up to `max_age`, when monitoring several
+    WgAll { max_age: time::Duration },
+
+    /// One handshake age per loop iteration, for testing
+    Replay(sync::Arc<replay::Replay>),
+}
+
+impl std::fmt::Display for HandshakeSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Wg => f.write_str("wg"),
+            Self::File(path) => write!(f, "file:{}", path.display()),
+            Self::WgAll { .. } => f.write_str("wg (all interfaces at once)"),
+            Self::Replay(replay) => write!(f, "replay:{}", replay.path().display()),
+        }
+    }
+}
+
+/// Parse a handshake source, either `wg`, `file:PATH` or `replay:PATH`.
+fn parse_handshake_source(s: &str) -> Result<HandshakeSource, String> {
+    if s == "wg" {
+        return Ok(HandshakeSource::Wg);
+    }
+
+    if let Some(path) = s.strip_prefix("replay:") {
+        return match path {
+            "" => Err("missing path after `replay:`".to_string()),
+            path => Ok(HandshakeSource::Replay(sync::Arc::new(replay::Replay::load(path.as_ref())?))),
+        };
+    }
+
+    match s.strip_prefix("file:") {
+        Some("") => Err("missing path after `file:`".to_string()),
+        Some(path) => Ok(HandshakeSource::File(path.into())),
+        None => Err(format!("expected `wg`, `file:PATH` or `replay:PATH`, got `{s}`")),
+    }
+}
+
+/// Received byte count as last seen, for `--mode rx-progress`.
+struct RxProgress {
+    last_total: Option<u64>,
+    changed_at: time::Instant,
+}
+
+impl RxProgress {
+    fn new() -> Self {
+        Self { last_total: None, changed_at: time::Instant::now() }
+    }
+
+    /// Record a new total, returning how long it has been since it last changed.
+    fn update(&mut self, total: u64) -> time::Duration {
+        // Any change counts, as counters reset when the interface is recreated
+        if self.last_total != Some(total) {
+            self.last_total = Some(total);
+            self.changed_at = time::Instant::now();
+        }
+
+        self.changed_at.elapsed()
+    }
+}
+
+/// State of the circuit breaker over restarts, for `--breaker-after`.
+#[derive(Clone, Copy, PartialEq)]
+enum BreakerState {
+    /// Restarting as usual
+    Closed,
+
+    /// Restarts have not been helping; none until `until`
+    Open { until: time::Instant, cooldown: time::Duration },
+
+    /// The cooldown passed and one trial restart was made; the next check decides
+    HalfOpen { cooldown: time::Duration },
+
+    /// Restarts did not help even at the longest cooldown; none until the connection recovers, for `--giveup-action idle`
+    GaveUp,
+}
+
+/// Get the raw output of `wg show <interface> <what>`.
+fn get_wg_show(runner: &CommandRunner, interface: &str, what: &str) -> Result<Vec<u8>, Error> {
+    let wg_show = runner.command("wg", &["show", interface, what])
+        .output()
+        .map_err(|e| Error::spawn(runner.spawned("wg"), e))?;
+
+    if !wg_show.status.success() {
+        return Err(Error::exit("wg show", &wg_show));
+    }
+
+    Ok(wg_show.stdout)
+}
+
+/// Get the names of all WireGuard interfaces on the host, by running `wg show interfaces`.
+fn get_wg_interfaces(runner: &CommandRunner) -> Result<Vec<String>, Error> {
+    let output = runner.command("wg", &["show", "interfaces"])
+        .output()
+        .map_err(|e| Error::spawn(runner.spawned("wg"), e))?;
+
+    if !output.status.success() {
+        return Err(Error::exit("wg show interfaces", &output));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).split_whitespace().map(str::to_string).collect())
+}
+
+/// Look up the public key of the peer named `name` in the `wg-quick` config of `interface`.
+fn get_peer_by_name(cli: &Cli, runner: &CommandRunner, interface: &str, name: &str) -> Result<String, Error> {
+    let path = match &cli.wg_config {
+        Some(path) => path.clone(),
+        None => path::PathBuf::from(format!("/etc/wireguard/{interface}.conf")),
+    };
+
+    // Read with `cat` so that it works over SSH as well
+    let output = runner.command("cat", &[&path.to_string_lossy()])
+        .output()
+        .map_err(|e| Error::spawn(runner.spawned("cat"), e))?;
+
+    if !output.status.success() {
+        return Err(Error::exit(&format!("cat {}", path.display()), &output));
+    }
+
+    let config = String::from_utf8_lossy(&output.stdout);
+
+    wg_quick::peer_public_key(&config, name)
+        .map(str::to_string)
+        .ok_or_else(|| Error::UnknownPeerName { name: name.to_string(), path })
+}
+
+/// A reading of one peer, or of the interface as a whole, as far as there was anything to read.
+enum Reading<T> {
+    Value(T),
+
+    /// `--peer` is not a peer of the interface
+    PeerAbsent,
+
+    /// The interface has no peers configured at all
+    NoPeers,
+}
+
+/// Get the number of bytes received from `peer`, or from all peers if none is given, by running `wg show`.
+fn get_rx_bytes(runner: &CommandRunner, interface: &str, peer: Option<&str>) -> Result<Reading<u64>, Error> {
+    let stdout = get_wg_show(runner, interface, "dump")?;
+
+    let peers = wg::parse_dump(&stdout)
+        .ok_or_else(|| Error::parse("wg show dump", &stdout))?;
+
+    if peers.is_empty() {
+        return Ok(Reading::NoPeers);
+    }
+
+    Ok(match peer {
+        Some(public_key) => peers
+            .iter()
+            .find(|p| p.public_key == public_key)
+            .map_or(Reading::PeerAbsent, |p| Reading::Value(p.rx_bytes)),
+        None => Reading::Value(peers.iter().map(|p| p.rx_bytes).sum()),
+    })
+}
+
+/// Get the total of bytes received and sent by `peer`, or by all peers if none is given.
+fn get_transfer_bytes(runner: &CommandRunner, interface: &str, peer: Option<&str>) -> Result<u64, Error> {
+    let stdout = get_wg_show(runner, interface, "dump")?;
+
+    let peers = wg::parse_dump(&stdout)
+        .ok_or_else(|| Error::parse("wg show dump", &stdout))?;
+
+    Ok(peers
+        .iter()
+        .filter(|p| peer.is_none_or(|public_key| p.public_key == public_key))
+        .map(|p| p.rx_bytes + p.tx_bytes)
+        .sum())
+}
+
+/// Get the longest persistent keepalive of `peer`, or of all peers if none is given, if any is configured.
+fn get_keepalive(runner: &CommandRunner, interface: &str, peer: Option<&str>) -> Result<Option<time::Duration>, Error> {
+    let stdout = get_wg_show(runner, interface, "dump")?;
+
+    let peers = wg::parse_dump(&stdout)
+        .ok_or_else(|| Error::parse("wg show dump", &stdout))?;
+
+    Ok(peers
+        .iter()
+        .filter(|p| peer.is_none_or(|public_key| p.public_key == public_key))
+        .filter_map(|p| p.persistent_keepalive)
+        .max()
+        .map(time::Duration::from_secs))
+}
+
+/// How far apart the two readings are that tell whether the tunnel is carrying traffic, for `--defer-if-active`.
+const TRAFFIC_SAMPLE: time::Duration = time::Duration::from_secs(2);
+
+/// Get the raw latest-handshakes output, by running `wg show` or from a file, depending on `source`.
+fn get_latest_handshakes(source: &HandshakeSource, runner: &CommandRunner, interface: &str) -> Result<Vec<u8>, Error> {
+    match source {
+        HandshakeSource::Wg => get_wg_show(runner, interface, "latest-handshakes"),
+        HandshakeSource::File(path) => std::fs::read(path)
+            .map_err(|source| Error::Read { path: path.clone(), source }),
+        HandshakeSource::WgAll { max_age } => {
+            let output = get_all_latest_handshakes(runner, *max_age)?;
+            wg::interface_output(&output, interface).ok_or_else(|| Error::parse("wg show all latest-handshakes", &output))
+        },
+        HandshakeSource::Replay(replay) => Ok(replay.output()),
+    }
+}
+
+/// `wg show all latest-handshakes` output as last read, and when, for `HandshakeSource::WgAll`.
+static ALL_LATEST_HANDSHAKES: sync::Mutex<Option<(time::Instant, Vec<u8>)>> = sync::Mutex::new(None);
+
+/// Get `wg show all latest-handshakes` output, reading it again only if what was last read is older than `max_age`.
+///
+/// The lock is held while reading, so that threads asking at the same time
+/// wait for the one read rather than each doing their own.
+fn get_all_latest_handshakes(runner: &CommandRunner, max_age: time::Duration) -> Result<Vec<u8>, Error> {
+    let mut cached = ALL_LATEST_HANDSHAKES.lock().unwrap_or_else(sync::PoisonError::into_inner);
+
+    if let Some((read_at, output)) = cached.as_ref()
+        && read_at.elapsed() < max_age
+    {
+        return Ok(output.clone());
+    }
+
+    let output = get_wg_show(runner, "all", "latest-handshakes")?;
+    *cached = Some((time::Instant::now(), output.clone()));
+    Ok(output)
+}
+
+/// Forget the shared `wg show all latest-handshakes` output, so that the next reading is fresh.
+fn forget_all_latest_handshakes() {
+    *ALL_LATEST_HANDSHAKES.lock().unwrap_or_else(sync::PoisonError::into_inner) = None;
+}
+
+/// Parse every peer's public key and handshake timestamp from latest-handshakes output, per `--handshake-column`.
+fn parse_handshakes<'o>(cli: &Cli, stdout: &'o [u8]) -> Result<Vec<(&'o str, u64)>, Error> {
+    const COMMAND: &str = "wg show latest-handshakes";
+
+    if cli.handshake_column == wg::HANDSHAKE_COLUMN {
+        return wg::peer_handshakes(stdout).ok_or_else(|| Error::parse(COMMAND, stdout));
+    }
+
+    wg::peer_handshakes_in_column(stdout, cli.handshake_column)
+        .map_err(|source| Error::Column { command: COMMAND.to_string(), source })
+}
+
+/// Get the latest-handshake timestamp of `peer`, or of the first peer if none is given.
+///
+/// With `--min-fresh-peers K` and no `peer`, this is the K-th newest handshake of all peers.
+fn get_handshake_ts(cli: &Cli, runner: &CommandRunner, interface: &str, peer: Option<&str>) -> Result<Reading<u64>, Error> {
+    let stdout = get_latest_handshakes(&cli.handshake_source, runner, interface)?;
+
+    // No peers means no output at all, which is not the same as output that does not parse
+    if stdout.trim_ascii().is_empty() {
+        return Ok(Reading::NoPeers);
+    }
+
+    match peer {
+        Some(public_key) => Ok(parse_handshakes(cli, &stdout)?
+            .iter()
+            .find(|&&(key, _)| key == public_key)
+            .map_or(Reading::PeerAbsent, |&(_, ts)| Reading::Value(ts))),
+        None if let Some(quorum) = cli.min_fresh_peers => {
+            let mut timestamps: Vec<u64> = parse_handshakes(cli, &stdout)?.iter().map(|&(_, ts)| ts).collect();
+            let peers = timestamps.len();
+
+            // The quorum is met for as long as the quorum-th newest handshake is fresh
+            timestamps.sort_unstable_by(|a, b| b.cmp(a));
+            timestamps
+                .get(quorum as usize - 1)
+                .map(|&ts| Reading::Value(ts))
+                .ok_or(Error::TooFewPeers { peers, quorum })
+        },
+        None if cli.handshake_column == wg::HANDSHAKE_COLUMN => wg::first_peer_handshake_ts(&stdout)
+            .map(Reading::Value)
+            .ok_or_else(|| Error::parse("wg show latest-handshakes", &stdout)),
+        None => Ok(parse_handshakes(cli, &stdout)?
+            .first()
+            .map_or(Reading::NoPeers, |&(_, ts)| Reading::Value(ts))),
+    }
+}
+
+/// Describe the age of a handshake timestamp for humans, e.g. "45s ago".
+fn describe_handshake_age(timestamp: u64) -> String {
+    if timestamp == 0 {
+        return "never".to_string();
+    }
+
+    match clock::age(time::SystemTime::now(), timestamp) {
+        Ok(elapsed) => format!("{}s ago", elapsed.as_secs()),
+        Err(ahead) => format!("{}s in the future", ahead.as_secs()),
+    }
+}
+
+/// Log a one-time snapshot of what is being monitored and with which settings.
+fn log_startup_summary(cli: &Cli, runner: &CommandRunner, interface: &str) {
+    let format_optional = |d: Option<time::Duration>| match d {
+        Some(d) => humantime::format_duration(d).to_string(),
+        None => "off".to_string(),
+    };
+
+    let retry_after = match cli.retry_keepalive_multiple {
+        Some(multiple) => format!("{multiple}x keepalive or {}", humantime::format_duration(cli.retry_after_unit_restart)),
+        None => humantime::format_duration(cli.retry_after_unit_restart).to_string(),
+    };
+
+    info!("timeout {}, loop interval {}, retry after restart {retry_after}, first handshake timeout {}",
+        humantime::format_duration(cli.timeout),
+        humantime::format_duration(cli.loop_interval),
+        format_optional(cli.first_handshake_timeout));
+
+    match &cli.handshake_source {
+        HandshakeSource::File(path) => warn!("reading handshakes from `{}` instead of running `wg`", path.display()),
+        HandshakeSource::Replay(replay) => warn!("replaying handshakes from `{}` instead of running `wg`", replay.path().display()),
+        HandshakeSource::Wg | HandshakeSource::WgAll { .. } => {},
+    }
+
+    let stdout = match get_latest_handshakes(&cli.handshake_source, runner, interface) {
+        Ok(stdout) => stdout,
+        Err(e) => {
+            warn!("{e}");
+            return;
+        }
+    };
+
+    let peers = match parse_handshakes(cli, &stdout) {
+        Ok(peers) => peers,
+        Err(e) => {
+            warn!("{e}");
+            return;
+        }
+    };
+
+    info!("interface `{interface}` has {} peer(s)", peers.len());
+
+    for (public_key, timestamp) in &peers {
+        debug!("peer {} last handshake {}", cli.peer_key(public_key), describe_handshake_age(*timestamp));
+    }
+
+    if let Some(peer) = cli.peer.as_deref()
+        && !peers.iter().any(|&(public_key, _)| public_key == peer)
+    {
+        warn!("peer {} given with --peer is not a peer of interface `{interface}`", cli.peer_key(peer));
+    }
+}
+
+/// Take `--sample-count` handshake readings `--sample-spacing` apart and aggregate them into one.
+///
+/// Samples are sorted by timestamp, so the oldest handshake (the worst reading) comes first.
+/// Returns early as soon as a reading finds nothing to read.
+fn sample_handshake_ts(cli: &Cli, runner: &CommandRunner, interface: &str, peer: Option<&str>) -> Result<Reading<u64>, Error> {
+    let mut samples = Vec::new();
+
+    for i in 0..cli.sample_count {
+        if i > 0 {
+            thread::sleep(cli.sample_spacing);
+        }
+
+        match get_handshake_ts(cli, runner, interface, peer)? {
+            Reading::Value(timestamp) => samples.push(timestamp),
+            reading => return Ok(reading),
+        }
+    }
+
+    samples.sort_unstable();
+
+    let timestamp = match cli.sample_aggregate {
+        SampleAggregate::Median => samples[samples.len() / 2],
+        SampleAggregate::Worst => samples[0],
+    };
+
+    if cli.sample_count > 1 {
+        debug!("handshake samples {samples:?}; using {timestamp}");
+    }
+
+    Ok(Reading::Value(timestamp))
+}
+
+/// Convert a UNIX timestamp (seconds since epoch) to SystemTime.
+fn unix_ts_to_system_time(ts: u64) -> time::SystemTime {
+    time::UNIX_EPOCH + time::Duration::from_secs(ts)
+}
+
+/// State of a systemd unit, as reported by `systemctl is-active`.
+#[derive(Clone, PartialEq, Eq)]
+enum UnitState {
+    Active,
+    Reloading,
+    Inactive,
+    Failed,
+    Activating,
+    Deactivating,
+    Other(String),
+}
+
+impl UnitState {
+    fn parse(s: &str) -> Self {
+        match s.trim() {
+            "active" => Self::Active,
+            "reloading" => Self::Reloading,
+            "inactive" => Self::Inactive,
+            "failed" => Self::Failed,
+            "activating" => Self::Activating,
+            "deactivating" => Self::Deactivating,
+            other => Self::Other(other.to_string()),
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        matches!(self, Self::Active | Self::Reloading)
+    }
+}
+
+impl std::fmt::Display for UnitState {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Active => "active",
+            Self::Reloading => "reloading",
+            Self::Inactive => "inactive",
+            Self::Failed => "failed",
+            Self::Activating => "activating",
+            Self::Deactivating => "deactivating",
+            Self::Other(s) => s,
+        })
+    }
+}
+
+/// Get the state of a systemd unit, over D-Bus if connected.
+fn get_systemd_unit_state(runner: &CommandRunner, dbus: Option<&dbus::Manager>, unit_name: &str) -> Result<UnitState, Error> {
+    with_retries(|| {
+        if let Some(manager) = dbus {
+            return manager.active_state(unit_name).map(|state| UnitState::parse(&state));
+        }
+
+        // `is-active` exits nonzero for anything but active, but still prints the state
+        let output = runner.command("systemctl", &["is-active", unit_name])
+            .output()
+            .map_err(|e| Error::spawn(runner.spawned("systemctl"), e))?;
+
+        match String::from_utf8_lossy(&output.stdout).trim() {
+            // Printing no state at all means `systemctl` itself (or ssh) failed
+            "" => Err(Error::systemctl("systemctl is-active", &output)),
+            state => Ok(UnitState::parse(state)),
+        }
+    })
+}
+
+/// Bounce a systemd unit with `systemctl <verb>`, e.g. `systemctl restart`.
+///
+/// This waits for the job to finish unless `no_block` is set, in which case
+/// it is only queued with `--no-block`.
+fn systemctl_restart(runner: &CommandRunner, unit_name: &str, verb: SystemctlVerb, no_block: bool, timeout: Option<time::Duration>) -> Result<(), Error> {
+    match no_block {
+        true => systemctl_bounded(runner, &["--no-block", verb.command(), unit_name], timeout),
+        false => systemctl_bounded(runner, &[verb.command(), unit_name], timeout),
+    }
+}
+
+/// Send `signal` to the main process of the unit with `systemctl kill`, for `--restart-signal`.
+fn systemctl_kill(runner: &CommandRunner, unit_name: &str, signal: Signal, timeout: Option<time::Duration>) -> Result<(), Error> {
+    systemctl_bounded(runner, &["kill", "-s", signal.name, "--kill-whom=main", unit_name], timeout)
+}
+
+/// Run `systemctl` with `args`, for at most `timeout`.
+fn systemctl_bounded(runner: &CommandRunner, args: &[&str], timeout: Option<time::Duration>) -> Result<(), Error> {
+    let mut child = runner.command("systemctl", args)
+        .stdout(process::Stdio::piped())
+        .stderr(process::Stdio::piped())
+        .spawn()
+        .map_err(|e| Error::spawn(runner.spawned("systemctl"), e))?;
+
+    let Some(status) = wait_bounded(&mut child, timeout).map_err(|e| Error::spawn(runner.spawned("systemctl"), e))? else {
+        return Err(Error::Timeout { command: format!("systemctl {}", args.join(" ")), timeout: timeout.unwrap_or_default() });
+    };
+
+    // Only a line or two, well within what the pipes buffer while waiting
+    let mut output = process::Output { status, stdout: Vec::new(), stderr: Vec::new() };
+    if let Some(mut stderr) = child.stderr.take() {
+        let _ = stderr.read_to_end(&mut output.stderr);
+    }
+
+    if !output.status.success() {
+        let verb = args.iter().find(|arg| !arg.starts_with('-')).unwrap_or(&args[0]);
+        return Err(Error::systemctl(&format!("systemctl {verb}"), &output));
+    }
+
+    Ok(())
+}
+
+/// Wait for `child` to exit, for at most `timeout`.
+///
+/// A child still running after that is killed, and `None` returned.
+fn wait_bounded(child: &mut process::Child, timeout: Option<time::Duration>) -> io::Result<Option<process::ExitStatus>> {
+    const POLL_INTERVAL: time::Duration = time::Duration::from_millis(100);
+
+    let Some(timeout) = timeout else {
+        return child.wait().map(Some);
+    };
+
+    let started = time::Instant::now();
+
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+
+        if started.elapsed() >= timeout {
+            child.kill()?;
+            child.wait()?;
+            return Ok(None);
+        }
+
+        thread::sleep(POLL_INTERVAL.min(timeout - started.elapsed()));
+    }
+}
+
+/// How many times an operation failing with a transient error is tried in all.
+const TRANSIENT_ATTEMPTS: u32 = 3;
+
+/// How long to wait before the first retry of a transient error; doubled for every retry after.
+const TRANSIENT_BACKOFF: time::Duration = time::Duration::from_secs(1);
+
+/// Run `attempt`, retrying it with a short backoff while it fails with an error that is likely transient.
+fn with_retries<T>(mut attempt: impl FnMut() -> Result<T, Error>) -> Result<T, Error> {
+    let mut backoff = TRANSIENT_BACKOFF;
+
+    for _ in 1..TRANSIENT_ATTEMPTS {
+        match attempt() {
+            Err(e) if e.is_transient() => {
+                warn!("{e}; retrying in {}s ...", backoff.as_secs());
+                thread::sleep(backoff);
+                backoff *= 2;
+            },
+            result => return result,
+        }
+    }
+
+    attempt()
+}
+
+/// Get the time a systemd unit last entered the active state, if it has, over D-Bus if connected.
+fn get_systemd_unit_active_since(runner: &CommandRunner, dbus: Option<&dbus::Manager>, unit_name: &str) -> Result<Option<time::SystemTime>, Error> {
+    if let Some(manager) = dbus {
+        return with_retries(|| manager.active_since(unit_name));
+    }
+
+    let show = |extra_args: &[&str]| {
+        let mut args = vec!["show", "-p", "ActiveEnterTimestamp", "--value"];
+        args.extend(extra_args);
+        args.push(unit_name);
+
+        let output = runner.command("systemctl", &args)
+            .output()
+            .map_err(|e| Error::spawn(runner.spawned("systemctl"), e))?;
+
+        match output.status.success() {
+            true => Ok(output),
+            false => Err(Error::systemctl("systemctl show -p ActiveEnterTimestamp", &output)),
+        }
+    };
+
+    // Fall back to the local time format on systemd older than 248, but not when systemd could not be reached at all
+    let output = with_retries(|| match show(&["--timestamp=unix"]) {
+        Err(e) if !e.is_transient() => show(&[]),
+        result => result,
+    })?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    match stdout.trim() {
+        "" => Ok(None),
+        value => clock::parse_systemd_timestamp(value)
+            .map(Some)
+            .ok_or_else(|| Error::parse("systemctl show -p ActiveEnterTimestamp", value.as_bytes())),
+    }
+}
+
+/// How long ago the unit was (re)started, if it was within `--unit-start-window`.
+fn get_recent_unit_start(cli: &Cli, runner: &CommandRunner, dbus: Option<&dbus::Manager>, unit_name: &str) -> Option<time::Duration> {
+    let window = cli.unit_start_window?;
+
+    if cli.backend == Backend::Script {
+        return None;
+    }
+
+    let since = match get_systemd_unit_active_since(runner, dbus, unit_name) {
+        Ok(since) => since?,
+        Err(e) => {
+            warn!("{e}");
+            return None;
+        }
+    };
+
+    let elapsed = time::SystemTime::now()
+        .duration_since(since)
+        .unwrap_or_default();
+
+    (elapsed <= window).then_some(elapsed)
+}
+
+/// Read the system uptime from `/proc/uptime`.
+fn get_system_uptime(runner: &CommandRunner) -> Result<time::Duration, Error> {
+    let output = runner.command("cat", &["/proc/uptime"])
+        .output()
+        .map_err(|e| Error::spawn(runner.spawned("cat"), e))?;
+
+    if !output.status.success() {
+        return Err(Error::exit("cat /proc/uptime", &output));
+    }
+
+    let contents = String::from_utf8_lossy(&output.stdout);
+
+    // Format is "UPTIME IDLE", both in fractional seconds
+    contents
+        .split_whitespace()
+        .next()
+        .and_then(|s| s.parse().ok())
+        .and_then(|secs| time::Duration::try_from_secs_f64(secs).ok())
+        .ok_or_else(|| Error::parse("cat /proc/uptime", &output.stdout))
+}
+
+/// Get the effective user ID that commands are run as.
+fn get_euid(runner: &CommandRunner) -> Result<u32, Error> {
+    let output = runner.command("id", &["-u"])
+        .output()
+        .map_err(|e| Error::spawn(runner.spawned("id"), e))?;
+
+    if !output.status.success() {
+        return Err(Error::exit("id -u", &output));
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .map_err(|_| Error::parse("id -u", &output.stdout))
+}
+
+/// Check if a connection is up by running the user-provided up-check command.
+fn get_script_is_up(runner: &CommandRunner, command_line: &str, interface: &str) -> Result<bool, Error> {
+    match runner.shell(command_line, interface).status() {
+        Ok(status) if status.success() => Ok(true),
+        Ok(_) => Ok(false),
+        Err(e) => Err(Error::spawn(runner.spawned("env"), e)),
+    }
+}
+
+/// Set up logging to stderr, or with `journal` to the journal, or to `file`, filtered by `RUST_LOG` and `--log-level`.
+///
+/// Each line is prefixed with the name of the thread that logged it, which
+/// for monitoring threads is the name of the interface. `env_logger` formats
+/// a whole record before writing it out under a lock, so lines logged from
+/// different threads never interleave.
+fn init_logging(level: Option<log::LevelFilter>, journal: bool, file: Option<logfile::RotatingFile>) {
+    let mut builder = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"));
+
+    builder.format(|buf, record| {
+        writeln!(buf, "[{} {:<5} {}] {}",
+            buf.timestamp_seconds(),
+            record.level(),
+            thread::current().name().unwrap_or("?"),
+            record.args())
+    });
+
+    if let Some(level) = level {
+        builder.filter_level(level);
+    }
+
+    if let Some(file) = file {
+        builder.target(env_logger::Target::Pipe(Box::new(file)));
+    }
+
+    let logger = builder.build();
+    let max_level = logger.filter();
+
+    let journal_error = match journal.then(journal::connect) {
+        Some(Ok(())) => {
+            log::set_boxed_logger(Box::new(journal::Logger::new(logger))).expect("logging is only set up once");
+            log::set_max_level(max_level);
+            return;
+        },
+        Some(Err(e)) => Some(e),
+        None => None,
+    };
+
+    log::set_boxed_logger(Box::new(logger)).expect("logging is only set up once");
+    log::set_max_level(max_level);
+
+    if let Some(e) = journal_error {
+        warn!("journal is not available ({e}); logging to stderr instead ...");
+    }
+}
+
+/// Run `--notify-command`, if any, with a notification about an event.
+///
+/// With `--journal`, the event is also sent to the journal with structured fields.
+fn notify(cli: &Cli, webhook: Option<&Webhook>, event: &notify::Event) {
+    let message = cli.notify_template.render(event);
+
+    if journal::is_connected() {
+        let elapsed = event.elapsed.map(|elapsed| elapsed.as_secs().to_string());
+        let timeout = event.timeout.as_secs().to_string();
+        let priority = match event.outcome {
+            "warning" | "restart failed" | "restart unverified" | "reboot failed" | "breaker open" | "gave up" => "4",
+            _ => "5",
+        };
+
+        let mut fields = vec![
+            ("MESSAGE", message.as_str()),
+            ("PRIORITY", priority),
+            ("WG_INTERFACE", event.interface),
+            ("WG_UNIT", event.unit),
+            ("WG_EVENT", event.outcome),
+            ("WG_TIMEOUT_SECS", timeout.as_str()),
+        ];
+
+        fields.extend(journal::message_id(event.outcome).map(|id| ("MESSAGE_ID", id)));
+        fields.extend(elapsed.as_deref().map(|elapsed| ("WG_ELAPSED_SECS", elapsed)));
+
+        if let Err(e) = journal::send(&fields) {
+            error!("failed to send event to the journal: {e}");
+        }
+    }
+
+    if let Some(webhook) = webhook {
+        webhook.send(event, &message);
+    }
+
+    let Some(command_line) = cli.notify_command.as_deref() else { return };
+
+    // `sh -c` assigns the arguments after the command line to $0, $1, ...
+    match CommandRunner::new(None)
+        .shell(command_line, event.interface)
+        .args(["wg_restarter", &message])
+        .status()
+    {
+        Ok(status) if status.success() => {},
+        Ok(status) => error!("notify command failed with status {}", status.code().unwrap_or(-1)),
+        Err(e) => error!("failed to execute notify command: {e}"),
+    }
+}
+
+/// Run `--on-pause-command` or `--on-resume-command`, logging rather than returning any failure.
+///
+/// Like the pause file itself, these are always local, even with `--ssh`.
+fn run_hook(name: &str, command_line: &str, interface: &str) {
+    match CommandRunner::new(None).shell(command_line, interface).status() {
+        Ok(status) if status.success() => {},
+        Ok(status) => error!("{name} failed with status {}", status.code().unwrap_or(-1)),
+        Err(e) => error!("failed to execute {name}: {e}"),
+    }
+}
+
+/// Name of a `ValueEnum` variant as given on the command line.
+fn value_name<T: ValueEnum>(value: &T) -> String {
+    value
+        .to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default()
+}
+
+/// The effective configuration, for `--print-config`.
+fn config_record(cli: &Cli) -> output::Record {
+    use serde_json::Value;
+
+    let duration = |d: time::Duration| Value::from(humantime::format_duration(d).to_string());
+    let optional_duration = |d: Option<time::Duration>| d.map(duration).unwrap_or(Value::Null);
+
+    let mut record = output::Record::new();
+    record.insert("interface".into(), cli.interface().map(Value::from).unwrap_or(Value::Null));
+    record.insert("unit".into(), cli.interface().map(|interface| cli.unit_name(interface).into()).unwrap_or(Value::Null));
+    record.insert("all".into(), cli.all.into());
+    record.insert("rescan_interval".into(), duration(cli.rescan_interval));
+    record.insert("ignore_interface".into(), cli.ignore_interface.clone().into());
+    record.insert("credential".into(), cli.credential.as_deref().map(Value::from).unwrap_or(Value::Null));
+    record.insert("profile".into(), value_name(&cli.profile).into());
+    record.insert("timeout".into(), duration(cli.timeout));
+    record.insert("hard_timeout".into(), optional_duration(cli.hard_timeout));
+    record.insert("loop_interval".into(), duration(cli.loop_interval));
+    record.insert("retry_after_unit_restart".into(), duration(cli.retry_after_unit_restart));
+    record.insert("verify_after_restart".into(), cli.verify_after_restart.into());
+    record.insert("retry_keepalive_multiple".into(), cli.retry_keepalive_multiple.map(Value::from).unwrap_or(Value::Null));
+    record.insert("restart_in_flight".into(), duration(cli.restart_in_flight));
+    record.insert("mode".into(), value_name(&cli.mode).into());
+    record.insert("warn_threshold".into(), cli.warn_threshold
+        .map(|t| humantime::format_duration(t.resolve(cli.timeout)).to_string().into())
+        .unwrap_or(Value::Null));
+    record.insert("overshoot_deadband".into(), cli.overshoot_deadband
+        .map(|t| humantime::format_duration(t.resolve(cli.timeout)).to_string().into())
+        .unwrap_or(Value::Null));
+    record.insert("handshake_freshness_hysteresis".into(), optional_duration(cli.handshake_freshness_hysteresis));
+    record.insert("first_handshake_timeout".into(), optional_duration(cli.first_handshake_timeout));
+    record.insert("endpoint_change_detect".into(), cli.endpoint_change_detect.into());
+    record.insert("max_endpoint_changes".into(), cli.max_endpoint_changes.into());
+    record.insert("endpoint_change_window".into(), duration(cli.endpoint_change_window));
+    record.insert("on_first_start_wait".into(), optional_duration(cli.first_start_wait()));
+    record.insert("min_fresh_peers".into(), cli.min_fresh_peers.map(Value::from).unwrap_or(Value::Null));
+    record.insert("peer".into(), cli.peer.as_deref().map(|peer| Value::from(cli.peer_key(peer))).unwrap_or(Value::Null));
+    record.insert("peer_name".into(), cli.peer_name.as_deref().map(Value::from).unwrap_or(Value::Null));
+    record.insert("wg_config".into(), cli.wg_config.as_deref().map(|path| path.display().to_string().into()).unwrap_or(Value::Null));
+    record.insert("peer_absent_threshold".into(), cli.peer_absent_threshold.into());
+    record.insert("on_peer_absent".into(), value_name(&cli.on_peer_absent).into());
+    record.insert("on_no_peers".into(), value_name(&cli.on_no_peers).into());
+    record.insert("on_clock_anomaly".into(), value_name(&cli.on_clock_anomaly).into());
+    record.insert("backend".into(), value_name(&cli.backend).into());
+    record.insert("unit_start_window".into(), optional_duration(cli.unit_start_window));
+    record.insert("restart_on_startup_if_stale".into(), cli.restart_on_startup_if_stale.into());
+    record.insert("metrics_textfile".into(), cli.metrics_textfile.as_deref().map(|path| Value::from(path.display().to_string())).unwrap_or(Value::Null));
+    record.insert("state_file".into(), cli.state_file.as_deref().map(|path| Value::from(path.display().to_string())).unwrap_or(Value::Null));
+    record.insert("post_boot_window".into(), optional_duration(cli.post_boot_window));
+    record.insert("min_uptime_before_acting".into(), optional_duration(cli.min_uptime_before_acting));
+    record.insert("ssh".into(), cli.ssh.as_deref().map(Value::from).unwrap_or(Value::Null));
+    record.insert("on_unit_stopped".into(), value_name(&cli.on_unit_stopped).into());
+    record.insert("pause_file".into(), cli.pause_file.as_deref().map(|path| path.display().to_string().into()).unwrap_or(Value::Null));
+    record.insert("on_pause_command".into(), cli.on_pause_command.as_deref().map(Value::from).unwrap_or(Value::Null));
+    record.insert("on_resume_command".into(), cli.on_resume_command.as_deref().map(Value::from).unwrap_or(Value::Null));
+    record.insert("up_check_command".into(), cli.up_check_command.as_deref().map(Value::from).unwrap_or(Value::Null));
+    record.insert("restart_timeout".into(), optional_duration(cli.restart_timeout));
+    record.insert("restart_command".into(), cli.restart_command.as_deref().map(Value::from).unwrap_or(Value::Null));
+    record.insert("systemctl_verb".into(), value_name(&cli.systemctl_verb).into());
+    record.insert("restart_no_block".into(), cli.restart_no_block.into());
+    record.insert("restart_signal".into(), cli.restart_signal.map(|signal| Value::from(signal.to_string())).unwrap_or(Value::Null));
+    record.insert("quiet_after".into(), cli.quiet_after.map(Value::from).unwrap_or(Value::Null));
+    record.insert("max_restarts_per".into(), cli.max_restarts_per
+        .map(|limit| format!("{}/{}", limit.count, humantime::format_duration(limit.window)).into())
+        .unwrap_or(Value::Null));
+    record.insert("handshake_source".into(), cli.handshake_source.to_string().into());
+    record.insert("handshake_column".into(), cli.handshake_column.into());
+    record.insert("sample_count".into(), cli.sample_count.into());
+    record.insert("sample_spacing".into(), duration(cli.sample_spacing));
+    record.insert("sample_aggregate".into(), value_name(&cli.sample_aggregate).into());
+    record.insert("dump_on_timeout".into(), cli.dump_on_timeout.as_ref().map(|p| p.display().to_string().into()).unwrap_or(Value::Null));
+    record.insert("dump_keep".into(), cli.dump_keep.into());
+    record.insert("notify_command".into(), cli.notify_command.as_deref().map(Value::from).unwrap_or(Value::Null));
+    record.insert("notify_recovery".into(), cli.notify_recovery.into());
+    record.insert("notify_cooldown".into(), optional_duration(cli.notify_cooldown));
+    record.insert("defer_if_active".into(), cli.defer_if_active.into());
+    record.insert("defer_window".into(), duration(cli.defer_window));
+    record.insert("notify_webhook".into(), cli.notify_webhook.iter().map(|url| http::redact(url)).collect::<Vec<_>>().into());
+    record.insert("webhook_timeout".into(), duration(cli.webhook_timeout));
+    record.insert("http_retries".into(), cli.http_retries.into());
+    record.insert("http_retry_backoff".into(), duration(cli.http_retry_backoff));
+    record.insert("aggregate_window".into(), optional_duration(cli.aggregate_window));
+
+    // Only whether a token is configured, never the token itself
+    let webhook_token = cli.webhook_token_file.is_some() || env::var_os(webhook::TOKEN_ENV_VAR).is_some();
+    record.insert("webhook_token".into(), if webhook_token { "[redacted]".into() } else { Value::Null });
+    record.insert("reboot_after".into(), cli.reboot_after.map(Value::from).unwrap_or(Value::Null));
+    record.insert("reboot_command".into(), cli.reboot_command.as_str().into());
+    record.insert("reboot_min_uptime".into(), duration(cli.reboot_min_uptime));
+    record.insert("breaker_after".into(), cli.breaker_after.map(Value::from).unwrap_or(Value::Null));
+    record.insert("breaker_cooldown".into(), duration(cli.breaker_cooldown));
+    record.insert("breaker_max_cooldown".into(), duration(cli.breaker_max_cooldown));
+    record.insert("giveup_action".into(), cli.giveup_action.as_ref().map(|action| value_name(action).into()).unwrap_or(Value::Null));
+    record.insert("fail_fast".into(), cli.fail_fast.into());
+    record.insert("stdin_driver".into(), cli.stdin_driver.into());
+    record.insert("check_config".into(), cli.check_config.into());
+    record.insert("startup_json".into(), cli.startup_json.into());
+    record.insert("simulate_timeout".into(), match cli.simulate_timeout {
+        Some(Some(every)) => every.into(),
+        Some(None) => "first".into(),
+        None => Value::Null,
+    });
+    record.insert("log_peer_key".into(), value_name(&cli.log_peer_key).into());
+    record.insert("journal".into(), cli.journal.into());
+    record.insert("log_file".into(), cli.log_file.as_ref().map(|path| path.display().to_string().into()).unwrap_or(Value::Null));
+    record.insert("log_max_size".into(), cli.log_max_size.into());
+    record.insert("log_keep".into(), cli.log_keep.into());
+    record
+}
+
+/// Print the WireGuard interfaces on the host with their peers and unit state, for `--list-interfaces`.
+fn list_interfaces(cli: &Cli) -> process::ExitCode {
+    let runner = CommandRunner::new(cli.ssh.clone());
+
+    let interfaces = match get_wg_interfaces(&runner) {
+        Ok(interfaces) => interfaces,
+        Err(e) => {
+            error!("{e}");
+            return process::ExitCode::FAILURE;
+        }
+    };
+
+    let records: Vec<output::Record> = interfaces
+        .iter()
+        .map(|interface| interface_record(cli, &runner, interface, &cli.unit_name(interface)))
+        .collect();
+
+    print!("{}", output::records(cli.output_format, &records));
+    process::ExitCode::SUCCESS
+}
+
+/// Describe `interface` and its unit as `--list-interfaces` does, with the age of its newest handshake.
+fn interface_record(cli: &Cli, runner: &CommandRunner, interface: &str, unit_name: &str) -> output::Record {
+    use serde_json::Value;
+
+    let handshakes = get_wg_show(runner, interface, "latest-handshakes").ok();
+    let peers = handshakes.as_deref().and_then(wg::peer_handshakes);
+
+    let newest_age = peers
+        .as_ref()
+        .and_then(|peers| peers.iter().map(|&(_, ts)| ts).filter(|&ts| ts > 0).max())
+        .map(|ts| clock::age(time::SystemTime::now(), ts).unwrap_or_default().as_secs());
+
+    let unit_state = match cli.backend {
+        Backend::Systemd | Backend::Dbus => get_systemd_unit_state(runner, None, unit_name).ok().map(|state| state.to_string()),
+        Backend::Script => None,
+    };
+
+    let mut record = output::Record::new();
+    record.insert("interface".into(), interface.into());
+    record.insert("unit".into(), unit_name.into());
+    record.insert("unit_state".into(), unit_state.map(Value::from).unwrap_or(Value::Null));
+    record.insert("peers".into(), peers.as_ref().map(|peers| Value::from(peers.len())).unwrap_or(Value::Null));
+    record.insert("latest_handshake_age_secs".into(), newest_age.map(Value::from).unwrap_or(Value::Null));
+    record
+}
+
+/// Print one JSON line with the effective configuration and what is about to be monitored, for `--startup-json`.
+///
+/// With `--all` or a pattern, `interface` is `None` and the interfaces are
+/// those matching right now; more may be picked up later.
+fn print_startup_json(cli: &Cli, interface: Option<&str>) {
+    use serde_json::Value;
+
+    let runner = CommandRunner::new(cli.ssh.clone());
+
+    let interfaces = match interface {
+        Some(interface) => vec![interface_record(cli, &runner, interface, &cli.unit_name(interface))],
+        None => match get_wg_interfaces(&runner) {
+            Ok(interfaces) => interfaces
+                .iter()
+                .filter(|interface| cli.interface_pattern().is_none_or(|pattern| glob::matches(pattern, interface)))
+                .filter(|interface| !cli.ignore_interface.iter().any(|pattern| glob::matches(pattern, interface)))
+                .map(|interface| interface_record(cli, &runner, interface, &cli.unit_name(interface)))
+                .collect(),
+            Err(e) => {
+                warn!("{e}");
+                Vec::new()
+            },
+        },
+    };
+
+    let startup = serde_json::json!({
+        "config": Value::Object(config_record(cli)),
+        "interfaces": interfaces.into_iter().map(Value::Object).collect::<Vec<_>>(),
+    });
+
+    println!("{startup}");
+}
+
+/// What one iteration of the monitoring loop concluded, and why.
+enum LoopDecision {
+    /// Handshake (or received byte count) is fresh
+    Healthy,
+
+    /// No handshake recorded yet, and not yet past `--first-handshake-timeout`
+    NoHandshakeYet,
+
+    /// Stale, but the unit was (re)started too recently to judge
+    InGrace,
+
+    /// Stale, but another restart would exceed `--max-restarts-per`
+    InCooldown,
+
+    /// Stale, but the system booted within `--post-boot-window`
+    PostBoot,
+
+    /// Stale, but the system has not been up for `--min-uptime-before-acting`
+    LowUptime,
+
+    /// Stale, but a restart was just done and has not taken effect yet
+    RestartInFlight,
+
+    /// Stale, but the circuit breaker is open
+    BreakerOpen,
+
+    /// Stale, but the tunnel is carrying traffic and `--defer-if-active` holds off the restart
+    Deferred,
+
+    /// Unit was stopped deliberately and monitoring is paused
+    Paused,
+
+    /// `--pause-file` exists and monitoring is paused for maintenance
+    Maintenance,
+
+    /// Unit is activating or deactivating
+    Transitioning,
+
+    /// `--peer` is missing from the interface
+    PeerAbsent,
+
+    /// Latest handshake is in the future and `--on-clock-anomaly skip` is set
+    ClockAnomaly,
+
+    /// Within the timeout after being stale, but not yet below `--handshake-freshness-hysteresis`
+    Recovering,
+
+    /// Not yet stale, but past `--warn-threshold`
+    NearTimeout,
+
+    /// Past the timeout, but not by more than `--overshoot-deadband`
+    InDeadband,
+
+    /// Stale and in need of a restart, stale for `elapsed` if known
+    Stale { elapsed: Option<time::Duration> },
+
+    /// Restart succeeded
+    Restarted,
+
+    /// Restart succeeded, and `--verify-after-restart` saw a fresh handshake after it
+    RestartVerified,
+
+    /// Restart succeeded, but `--verify-after-restart` saw no fresh handshake within the retry interval
+    RestartUnverified,
+
+    /// Restart failed or could not be run
+    RestartFailed,
+
+    /// Reboot was attempted
+    Rebooted,
+
+    /// State could not be read, with the reason why
+    ShowError(Error),
+
+    /// Monitoring should end with this exit code
+    Exit(process::ExitCode),
+}
+
+impl LoopDecision {
+    /// Short name, suitable as a metric label.
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Healthy => "healthy",
+            Self::NoHandshakeYet => "no_handshake_yet",
+            Self::InGrace => "in_grace",
+            Self::InCooldown => "in_cooldown",
+            Self::PostBoot => "post_boot",
+            Self::LowUptime => "low_uptime",
+            Self::RestartInFlight => "restart_in_flight",
+            Self::BreakerOpen => "breaker_open",
+            Self::Deferred => "deferred",
+            Self::Paused => "paused",
+            Self::Maintenance => "maintenance",
+            Self::Transitioning => "transitioning",
+            Self::PeerAbsent => "peer_absent",
+            Self::ClockAnomaly => "clock_anomaly",
+            Self::Recovering => "recovering",
+            Self::NearTimeout => "near_timeout",
+            Self::InDeadband => "in_deadband",
+            Self::Stale { .. } => "stale",
+            Self::Restarted => "restarted",
+            Self::RestartVerified => "restart_verified",
+            Self::RestartUnverified => "restart_unverified",
+            Self::RestartFailed => "restart_failed",
+            Self::Rebooted => "rebooted",
+            Self::ShowError(_) => "show_error",
+            Self::Exit(_) => "exit",
+        }
+    }
+
+    /// How long to wait before the next iteration, `retry_after` being how long after a restart.
+    fn sleep_duration(&self, cli: &Cli, retry_after: time::Duration) -> time::Duration {
+        match self {
+            Self::Restarted | Self::RestartFailed | Self::Rebooted => retry_after,
+            Self::RestartUnverified => time::Duration::ZERO,
+            _ => cli.loop_interval,
+        }
+    }
+}
+
+/// State carried between iterations of the monitoring loop of one interface.
+struct Monitor<'a> {
+    cli: &'a Cli,
+    runner: &'a CommandRunner,
+
+    /// Connection to systemd with `--backend dbus`, unless it could not be made
+    dbus: Option<&'a dbus::Manager>,
+    webhook: Option<&'a Webhook>,
+
+    /// Where restart notifications are held back with `--aggregate-window`
+    aggregator: Option<&'a Aggregator>,
+    interface: &'a str,
+    unit_name: String,
+
+    /// What gets run to restart the connection, for display purposes
+    restart_description: String,
+
+    /// Reset on every restart so a never-handshaked unit gets a fresh chance
+    waiting_since: time::Instant,
+    restart_history: RestartHistory,
+    unrecovered_restarts: u32,
+
+    /// When the last successful restart was done, until a reading shows it took effect
+    restart_in_flight: Option<time::Instant>,
+
+    /// When the last restart was attempted, for `--hard-timeout`
+    restarted_at: Option<time::Instant>,
+
+    /// Since when a restart has been deferred by `--defer-if-active`
+    deferring_since: Option<time::Instant>,
+
+    /// How long to wait after the last restart, per `--retry-keepalive-multiple`
+    retry_after: time::Duration,
+
+    /// Persistent keepalive as read before the last restart, once read, for `--retry-keepalive-multiple`
+    keepalive: Option<Option<time::Duration>>,
+    restart_failures: Option<failures::RestartFailures>,
+    breaker: BreakerState,
+    notify_cooldown: NotifyCooldown,
+    paused: bool,
+
+    /// Whether `--pause-file` existed at the last check, to run hooks only on changes
+    maintenance: bool,
+    rx_progress: RxProgress,
+    endpoints: EndpointHistory,
+
+    /// Consecutive checks in which `--peer` was missing
+    peer_absent_checks: u32,
+
+    /// Whether the interface was last seen without any peers
+    no_peers: bool,
+
+    /// Whether staleness is currently past `--warn-threshold`, to warn only on crossing it
+    past_warn_threshold: bool,
+
+    /// Set when found stale, to how stale if known, until the connection recovers
+    stale: Option<Option<time::Duration>>,
+
+    /// What was last written to `--state-file`, to only write it again when something changed
+    saved_state: Option<state::Saved>,
+
+    /// Loop iterations so far, for `--simulate-timeout`
+    iterations: u64,
+
+    /// Whether the last successful restart is yet to be judged by whether the connection recovered after it
+    awaiting_effect: bool,
+
+    /// Totals for the exit summary
+    started_at: time::Instant,
+    warnings: u32,
+    recoveries: u32,
+    breaker_trips: u32,
+    restarts: u32,
+    failed_restarts: u32,
+
+    /// Restarts after which the connection recovered, and those after which it did not
+    effective_restarts: u32,
+    ineffective_restarts: u32,
+    reboots: u32,
+}
+
+impl<'a> Monitor<'a> {
+    fn new(cli: &'a Cli, runner: &'a CommandRunner, dbus: Option<&'a dbus::Manager>, webhook: Option<&'a Webhook>, interface: &'a str, unit_name: String, restart_description: String) -> Self {
+        Self {
+            cli,
+            runner,
+            dbus,
+            webhook,
+            aggregator: None,
+            saved_state: None,
+            interface,
+            unit_name,
+            restart_description,
+            waiting_since: time::Instant::now(),
+            restart_history: RestartHistory::new(),
+            unrecovered_restarts: 0,
+            restart_in_flight: None,
+            restarted_at: None,
+            deferring_since: None,
+            retry_after: cli.retry_after_unit_restart,
+            keepalive: None,
+            restart_failures: cli.quiet_after.map(failures::RestartFailures::new),
+            breaker: BreakerState::Closed,
+            notify_cooldown: NotifyCooldown::new(),
+            paused: false,
+            maintenance: false,
+            rx_progress: RxProgress::new(),
+            endpoints: EndpointHistory::new(),
+            peer_absent_checks: 0,
+            no_peers: false,
+            past_warn_threshold: false,
+            stale: None,
+            iterations: 0,
+            awaiting_effect: false,
+            started_at: time::Instant::now(),
+            warnings: 0,
+            recoveries: 0,
+            breaker_trips: 0,
+            restarts: 0,
+            failed_restarts: 0,
+            effective_restarts: 0,
+            ineffective_restarts: 0,
+            reboots: 0,
+        }
+    }
+
+    /// Totals of what was done while monitoring, for printing on exit.
+    fn summary(&self) -> output::Record {
+        let mut record = output::Record::new();
+        record.insert("interface".into(), self.interface.into());
+        record.insert("unit".into(), self.unit_name.as_str().into());
+        record.insert("monitored_secs".into(), self.started_at.elapsed().as_secs().into());
+        record.insert("warnings".into(), self.warnings.into());
+        record.insert("recoveries".into(), self.recoveries.into());
+        record.insert("breaker_trips".into(), self.breaker_trips.into());
+        record.insert("restarts".into(), self.restarts.into());
+        record.insert("failed_restarts".into(), self.failed_restarts.into());
+        record.insert("effective_restarts".into(), self.effective_restarts.into());
+        record.insert("ineffective_restarts".into(), self.ineffective_restarts.into());
+        record.insert("reboots".into(), self.reboots.into());
+        record
+    }
+
+    /// Check the unit and connection, and decide whether it needs a restart.
+    fn check(&mut self) -> LoopDecision {
+        self.iterations += 1;
+        self.flush_notify_cooldown();
+
+        let maintenance = self.check_pause_file();
+
+        if let Some(elapsed) = self.check_hard_timeout() {
+            return self.hard_restart(elapsed);
+        }
+
+        if maintenance {
+            return LoopDecision::Maintenance;
+        }
+
+        let unit_name = &self.unit_name;
+
+        let unit_state = match self.cli.backend {
+            Backend::Systemd | Backend::Dbus => match get_systemd_unit_state(self.runner, self.dbus, unit_name) {
+                Ok(state) => Some(state),
+                Err(e) => return LoopDecision::ShowError(e),
+            },
+            Backend::Script => None,
+        };
+
+        match &unit_state {
+            Some(UnitState::Inactive) if self.cli.on_unit_stopped == OnUnitStopped::Exit => {
+                info!("systemd service `{unit_name}` was stopped; exiting ...");
+                return LoopDecision::Exit(process::ExitCode::SUCCESS);
+            },
+            Some(UnitState::Inactive) if self.cli.on_unit_stopped == OnUnitStopped::Pause => {
+                if !self.paused {
+                    info!("systemd service `{unit_name}` was stopped; pausing until it is started again ...");
+                    self.paused = true;
+                }
+
+                return LoopDecision::Paused;
+            },
+            Some(state @ (UnitState::Activating | UnitState::Deactivating)) => {
+                info!("systemd service `{unit_name}` is {state}; waiting ...");
+                return LoopDecision::Transitioning;
+            },
+            _ if self.paused => {
+                info!("systemd service `{unit_name}` was started again; resuming ...");
+                self.paused = false;
+                self.waiting_since = time::Instant::now();
+            },
+            _ => {},
+        }
+
+        if let Some(state) = unit_state.filter(|state| !state.is_active()) {
+            warn!("systemd service `{unit_name}` is not active ({state}); restarting service ...");
+            return LoopDecision::Stale { elapsed: None };
+        }
+
+        // The script backend's counterpart to an inactive unit
+        if let Backend::Script = self.cli.backend {
+            let up_check_command = self.cli.up_check_command.as_deref().expect("clap requires --up-check-command");
+
+            match get_script_is_up(self.runner, up_check_command, self.interface) {
+                Ok(true) => {},
+                Ok(false) => {
+                    warn!("up-check command reports interface `{}` is not up; restarting connection ...", self.interface);
+                    return LoopDecision::Stale { elapsed: None };
+                },
+                Err(e) => return LoopDecision::ShowError(e),
+            }
+        }
+
+        let decision = match self.cli.mode {
+            Mode::Handshake => self.check_handshake(),
+            Mode::RxProgress => self.check_rx_progress(),
+        };
+
+        let decision = match decision {
+            LoopDecision::Healthy | LoopDecision::Recovering | LoopDecision::NearTimeout | LoopDecision::InDeadband
+            | LoopDecision::InGrace | LoopDecision::NoHandshakeYet if self.cli.endpoint_change_detect => {
+                self.check_endpoints().unwrap_or(decision)
+            },
+            decision => decision,
+        };
+
+        if let LoopDecision::Healthy | LoopDecision::Recovering | LoopDecision::NearTimeout = decision {
+            self.restart_in_flight = None;
+            self.deferring_since = None;
+        }
+
+        if let LoopDecision::Healthy = decision {
+            self.unrecovered_restarts = 0;
+
+            if self.awaiting_effect {
+                self.judge_restart(true);
+            }
+
+            if !matches!(self.breaker, BreakerState::Closed) {
+                info!("connection recovered; closing circuit breaker ...");
+                self.breaker = BreakerState::Closed;
+                self.notify(None, "breaker closed");
+            }
+        }
+
+        decision
+    }
+
+    /// Check the latest handshake against `--hard-timeout`, returning its age if it is past it.
+    ///
+    /// The age counts from the last restart if that was more recent, so that
+    /// a restart gets as long to take effect as the ceiling itself.
+    fn check_hard_timeout(&self) -> Option<time::Duration> {
+        let hard_timeout = self.cli.hard_timeout?;
+
+        // A unit stopped on purpose stays stopped
+        if self.paused {
+            return None;
+        }
+
+        let elapsed = match get_handshake_ts(self.cli, self.runner, self.interface, self.cli.peer.as_deref()) {
+            // No handshake at all is left to `--first-handshake-timeout`
+            Ok(Reading::Value(0) | Reading::NoPeers | Reading::PeerAbsent) => return None,
+            Ok(Reading::Value(timestamp)) => {
+                let age = clock::age(time::SystemTime::now(), timestamp).ok()?;
+                self.restarted_at.map_or(age, |restarted_at| age.min(restarted_at.elapsed()))
+            },
+            Err(e) => {
+                debug!("{e}; skipping --hard-timeout check ...");
+                return None;
+            },
+        };
+
+        (elapsed > hard_timeout).then_some(elapsed)
+    }
+
+    /// Restart past `--hard-timeout`, bypassing everything but the rate limit.
+    fn hard_restart(&mut self, elapsed: time::Duration) -> LoopDecision {
+        let cli = self.cli;
+        let hard_timeout = cli.hard_timeout.expect("only called with --hard-timeout");
+
+        if self.low_uptime() {
+            return LoopDecision::LowUptime;
+        }
+
+        if let Some(limit) = &cli.max_restarts_per
+            && !self.restart_history.allows(limit, time::Instant::now())
+        {
+            error!("handshake age {}s > --hard-timeout {}s, but rate limit reached; {} restarts within the last {}. not restarting ...",
+                elapsed.as_secs(), hard_timeout.as_secs(), limit.count, humantime::format_duration(limit.window));
+            return LoopDecision::InCooldown;
+        }
+
+        error!("!!! handshake age {}s > --hard-timeout {}s; restarting regardless of maintenance, grace periods and the circuit breaker !!!",
+            elapsed.as_secs(), hard_timeout.as_secs());
+
+        self.stale = Some(Some(elapsed));
+        self.restart(Some(elapsed))
+    }
+
+    /// Whether this iteration should be treated as a timeout due to `--simulate-timeout`.
+    fn simulate_timeout(&self) -> bool {
+        match self.cli.simulate_timeout {
+            Some(Some(every)) => self.iterations.is_multiple_of(every.into()),
+            Some(None) => self.iterations == 1,
+            None => false,
+        }
+    }
+
+    /// Check for `--pause-file`, running the pause and resume hooks when it appears or goes away.
+    ///
+    /// Returns whether monitoring is paused for maintenance.
+    fn check_pause_file(&mut self) -> bool {
+        let Some(path) = &self.cli.pause_file else { return false };
+        let exists = path.exists();
+
+        if exists == self.maintenance {
+            return exists;
+        }
+
+        self.maintenance = exists;
+
+        if exists {
+            info!("pause file `{}` exists; pausing for maintenance ...", path.display());
+
+            if let Some(command_line) = &self.cli.on_pause_command {
+                run_hook("pause command", command_line, self.interface);
+            }
+        } else {
+            info!("pause file `{}` was removed; resuming ...", path.display());
+            self.waiting_since = time::Instant::now();
+            self.rx_progress = RxProgress::new();
+
+            if let Some(command_line) = &self.cli.on_resume_command {
+                run_hook("resume command", command_line, self.interface);
+            }
+        }
+
+        exists
+    }
+
+    /// Check whether any bytes were received since the last check.
+    fn check_rx_progress(&mut self) -> LoopDecision {
+        let cli = self.cli;
+
+        let (runner, interface) = (self.runner, self.interface);
+
+        let rx_bytes = match self.read_peer(|peer| get_rx_bytes(runner, interface, peer)) {
+            Ok(Reading::Value(v)) => v,
+            Ok(Reading::PeerAbsent) => return LoopDecision::PeerAbsent,
+            Ok(Reading::NoPeers) => match self.no_peers() {
+                Some(decision) => return decision,
+                None => 0,
+            },
+            Err(e) => return LoopDecision::ShowError(e),
+        };
+
+        let stalled = self.rx_progress.update(rx_bytes);
+
+        if let Staleness::Fresh = cli.staleness(stalled) {
+            debug!("received {rx_bytes} bytes; unchanged for {}s <= {}s", stalled.as_secs(), cli.timeout.as_secs());
+            return self.fresh(stalled, "received bytes unchanged");
+        }
+
+        if self.in_deadband(stalled, "received bytes unchanged") {
+            return LoopDecision::InDeadband;
+        }
+
+        warn!("received bytes unchanged; {}s > {}s. restarting service ...", stalled.as_secs(), cli.timeout.as_secs());
+        LoopDecision::Stale { elapsed: Some(stalled) }
+    }
+
+    /// Check the age of the latest handshake.
+    fn check_handshake(&mut self) -> LoopDecision {
+        let cli = self.cli;
+
+        let (runner, interface) = (self.runner, self.interface);
+
+        let timestamp = match self.read_peer(|peer| sample_handshake_ts(cli, runner, interface, peer)) {
+            Ok(Reading::Value(v)) => v,
+            Ok(Reading::PeerAbsent) => return LoopDecision::PeerAbsent,
+            Ok(Reading::NoPeers) => match self.no_peers() {
+                Some(decision) => return decision,
+                None => 0,  // as if no handshake was recorded
+            },
+            Err(e) => return LoopDecision::ShowError(e),
+        };
+
+        if timestamp == 0 {
+            // A unit restarted by someone else resets the wait too
+            let waited = match get_recent_unit_start(cli, self.runner, self.dbus, &self.unit_name) {
+                Some(since_start) => since_start.min(self.waiting_since.elapsed()),
+                None => self.waiting_since.elapsed(),
+            };
+
+            // The unit may have gone without a handshake for long before monitoring started
+            let waited = match self.is_startup_check() {
+                true => waited.max(self.unit_active_for().unwrap_or_default()),
+                false => waited,
+            };
+
+            return match cli.first_handshake_timeout {
+                Some(first_timeout) if waited > first_timeout => {
+                    warn!("no handshake recorded; {}s > {}s. restarting service ...", waited.as_secs(), first_timeout.as_secs());
+                    LoopDecision::Stale { elapsed: Some(waited) }
+                },
+                first_timeout => {
+                    let message = match first_timeout {
+                        Some(first_timeout) => format!("no handshake recorded yet after {}s; restart at {}s ...", waited.as_secs(), first_timeout.as_secs()),
+                        None => format!("no handshake recorded yet after {}s; waiting ...", waited.as_secs()),
+                    };
+
+                    match cli.first_start_wait() {
+                        Some(wait) if waited > wait => warn!("{message}"),
+                        _ => info!("{message}"),
+                    }
+
+                    LoopDecision::NoHandshakeYet
+                }
+            };
+        }
+
+        let elapsed = match cli.on_clock_anomaly.age(time::SystemTime::now(), timestamp, "latest handshake") {
+            HandshakeAge::Age(elapsed) => elapsed,
+            HandshakeAge::Restart => return LoopDecision::Stale { elapsed: None },
+            HandshakeAge::Skip => return LoopDecision::ClockAnomaly,
+        };
+
+        if let Staleness::Fresh = cli.staleness(elapsed) {
+            debug!("handshake age {}s <= {}s", elapsed.as_secs(), cli.timeout.as_secs());
+            return self.fresh(elapsed, "handshake age");
+        }
+
+        if let Some(since_start) = get_recent_unit_start(cli, self.runner, self.dbus, &self.unit_name)
+            && since_start <= cli.timeout
+            && !self.is_startup_check()
+        {
+            info!("handshake age {}s, but unit started {}s ago; waiting ...", elapsed.as_secs(), since_start.as_secs());
+            return LoopDecision::InGrace;
+        }
+
+        if self.in_deadband(elapsed, "handshake age") {
+            return LoopDecision::InDeadband;
+        }
+
+        warn!("handshake timeout; {}s > {}s. restarting service ...", elapsed.as_secs(), cli.timeout.as_secs());
+        LoopDecision::Stale { elapsed: Some(elapsed) }
+    }
+
+    /// Whether staleness past the timeout is still within `--overshoot-deadband`.
+    fn in_deadband(&self, elapsed: time::Duration, what: &str) -> bool {
+        let cli = self.cli;
+        let Staleness::InDeadband(deadband) = cli.staleness(elapsed) else { return false };
+
+        debug!("{what} {}s > {}s, but within the deadband of {}s; not restarting ...",
+            elapsed.as_secs(), cli.timeout.as_secs(), deadband.as_secs());
+        true
+    }
+
+    /// Write `--metrics-textfile`, with `decision` as what the last check decided.
+    fn write_metrics(&self, decision: &'static str) {
+        let Some(path) = &self.cli.metrics_textfile else { return };
+
+        let sample = metrics::Sample {
+            unit: self.unit_name.clone(),
+            restarts: self.restarts,
+            failed_restarts: self.failed_restarts,
+            effective_restarts: self.effective_restarts,
+            ineffective_restarts: self.ineffective_restarts,
+            reboots: self.reboots,
+            recoveries: self.recoveries,
+            breaker_trips: self.breaker_trips,
+            warnings: self.warnings,
+            decision,
+            updated_at: time::SystemTime::now(),
+        };
+
+        if let Err(e) = metrics::update(path, self.interface, sample) {
+            error!("{e}");
+        }
+    }
+
+    /// Carry on from what `--state-file` says about this interface, if anything.
+    fn restore_state(&mut self) {
+        let Some(path) = &self.cli.state_file else { return };
+
+        let saved = match state::load(path, self.interface) {
+            Ok(Some(saved)) => saved,
+            Ok(None) => return,
+            Err(e) => {
+                warn!("{e}; starting afresh ...");
+                return;
+            },
+        };
+
+        let breaker = match saved.breaker {
+            BreakerState::Closed => "closed".to_string(),
+            BreakerState::Open { until, .. } => format!("open for another {}s", until.saturating_duration_since(time::Instant::now()).as_secs()),
+            BreakerState::HalfOpen { .. } => "half-open".to_string(),
+            BreakerState::GaveUp => "given up".to_string(),
+        };
+
+        info!("restored state from `{}`: {} restart(s) without recovery, circuit breaker {breaker}",
+            path.display(), saved.unrecovered_restarts);
+
+        self.unrecovered_restarts = saved.unrecovered_restarts;
+        self.breaker = saved.breaker;
+
+        // So that a recovery is noticed as one
+        if saved.unrecovered_restarts > 0 {
+            self.stale = Some(None);
+        }
+
+        self.saved_state = Some(saved);
+    }
+
+    /// Write what `--state-file` keeps about this interface, if it changed.
+    fn save_state(&mut self) {
+        let Some(path) = &self.cli.state_file else { return };
+
+        let saved = state::Saved {
+            unrecovered_restarts: self.unrecovered_restarts,
+            breaker: self.breaker,
+        };
+
+        if self.saved_state.as_ref() == Some(&saved) {
+            return;
+        }
+
+        match state::save(path, self.interface, &saved) {
+            Ok(()) => self.saved_state = Some(saved),
+            Err(e) => error!("{e}"),
+        }
+    }
+
+    /// Whether this is the first check and `--restart-on-startup-if-stale` is set, so grace periods are skipped.
+    fn is_startup_check(&self) -> bool {
+        self.cli.restart_on_startup_if_stale && self.iterations == 1
+    }
+
+    /// How long the unit has been active, if it is and that can be told.
+    fn unit_active_for(&self) -> Option<time::Duration> {
+        if self.cli.backend == Backend::Script {
+            return None;
+        }
+
+        match get_systemd_unit_active_since(self.runner, self.dbus, &self.unit_name) {
+            Ok(since) => since.and_then(|since| since.elapsed().ok()),
+            Err(e) => {
+                warn!("{e}");
+                None
+            },
+        }
+    }
+
+    /// Check whether the endpoint of a peer keeps changing without a handshake, for `--endpoint-change-detect`.
+    fn check_endpoints(&mut self) -> Option<LoopDecision> {
+        let cli = self.cli;
+
+        let stdout = match get_wg_show(self.runner, self.interface, "dump") {
+            Ok(stdout) => stdout,
+            Err(e) => return Some(LoopDecision::ShowError(e)),
+        };
+
+        let Some(mut peers) = wg::parse_dump(&stdout) else {
+            return Some(LoopDecision::ShowError(Error::parse("wg show dump", &stdout)));
+        };
+
+        if let Some(public_key) = cli.peer.as_deref() {
+            peers.retain(|peer| peer.public_key == public_key);
+        }
+
+        let (public_key, changes) = self.endpoints.update(time::SystemTime::now(), &peers, cli.endpoint_change_window, cli.max_endpoint_changes)?;
+
+        warn!("endpoint of peer {} changed {changes} times within {}s without a handshake; restarting service ...",
+            cli.peer_key(public_key), cli.endpoint_change_window.as_secs());
+        Some(LoopDecision::Stale { elapsed: None })
+    }
+
+    /// Decide on a reading within the timeout, noting a recovery if the connection was stale before.
+    fn fresh(&mut self, elapsed: time::Duration, what: &str) -> LoopDecision {
+        let cli = self.cli;
+
+        // Restarts still only happen past the timeout; the margin only delays declaring a recovery
+        if self.stale.is_some()
+            && let Some(margin) = cli.handshake_freshness_hysteresis
+            && elapsed > cli.timeout - margin
+        {
+            debug!("{what} {}s, not yet below {}s; not recovered yet ...", elapsed.as_secs(), (cli.timeout - margin).as_secs());
+            return LoopDecision::Recovering;
+        }
+
+        let decision = self.check_warn_threshold(elapsed, what);
+
+        if let LoopDecision::Healthy = decision
+            && let Some(was) = self.stale.take()
+        {
+            match was {
+                Some(was) => info!("tunnel recovered; {what} now {}s (was {}s)", elapsed.as_secs(), was.as_secs()),
+                None => info!("tunnel recovered; {what} now {}s", elapsed.as_secs()),
+            }
+
+            self.recoveries += 1;
+
+            if self.cli.notify_recovery {
+                self.notify(was, "recovered");
+            }
+        }
+
+        decision
+    }
+
+    /// Decide between healthy and near the timeout, warning and notifying when `--warn-threshold` is first crossed.
+    fn check_warn_threshold(&mut self, elapsed: time::Duration, what: &str) -> LoopDecision {
+        let cli = self.cli;
+
+        let Some(threshold) = cli.warn_threshold.map(|t| t.resolve(cli.timeout)) else {
+            return LoopDecision::Healthy;
+        };
+
+        if elapsed < threshold {
+            if self.past_warn_threshold {
+                info!("{what} {}s back below warn threshold {}s", elapsed.as_secs(), threshold.as_secs());
+                self.past_warn_threshold = false;
+            }
+
+            return LoopDecision::Healthy;
+        }
+
+        if !self.past_warn_threshold {
+            warn!("{what} {}s past warn threshold {}s; restart at {}s ...", elapsed.as_secs(), threshold.as_secs(), cli.timeout.as_secs());
+            self.past_warn_threshold = true;
+            self.warnings += 1;
+            self.notify(Some(elapsed), "warning");
+        }
+
+        LoopDecision::NearTimeout
+    }
+
+    /// Take a reading of `--peer`, or of the interface as a whole without it.
+    ///
+    /// If the peer is missing for long enough that `--on-peer-absent fallback` applies,
+    /// the interface is read instead.
+    fn read_peer<T>(&mut self, read: impl Fn(Option<&str>) -> Result<Reading<T>, Error>) -> Result<Reading<T>, Error> {
+        let reading = match self.cli.peer.as_deref() {
+            None => read(None)?,
+            Some(peer) => match read(Some(peer))? {
+                Reading::PeerAbsent if self.peer_absent() => read(None)?,
+                reading => {
+                    if let Reading::Value(_) = reading {
+                        self.peer_present();
+                    }
+
+                    reading
+                }
+            },
+        };
+
+        if !matches!(reading, Reading::NoPeers) {
+            self.peers_configured();
+        }
+
+        Ok(reading)
+    }
+
+    /// Handle the interface having no peers, logging it only the first time.
+    ///
+    /// Returns `None` if monitoring should carry on as if nothing had been received, per `--on-no-peers wait`.
+    fn no_peers(&mut self) -> Option<LoopDecision> {
+        let on_no_peers = self.cli.on_no_peers;
+
+        if !self.no_peers {
+            self.no_peers = true;
+
+            match on_no_peers {
+                OnNoPeers::Exit => error!("interface `{}` has no peers configured; exiting ...", self.interface),
+                OnNoPeers::Pause => warn!("interface `{}` has no peers configured; pausing until it does ...", self.interface),
+                OnNoPeers::Wait => warn!("interface `{}` has no peers configured; waiting ...", self.interface),
+            }
+        }
+
+        match on_no_peers {
+            OnNoPeers::Exit => Some(LoopDecision::Exit(process::ExitCode::FAILURE)),
+            OnNoPeers::Pause => Some(LoopDecision::Paused),
+            OnNoPeers::Wait => None,
+        }
+    }
+
+    /// Note that the interface has peers, after possibly having had none.
+    fn peers_configured(&mut self) {
+        if !self.no_peers {
+            return;
+        }
+
+        info!("interface `{}` has peers configured again; resuming ...", self.interface);
+        self.no_peers = false;
+
+        if self.cli.on_no_peers == OnNoPeers::Pause {
+            self.waiting_since = time::Instant::now();
+        }
+    }
+
+    /// Note that `--peer` was found, after possibly having been missing.
+    fn peer_present(&mut self) {
+        if self.peer_absent_checks >= self.cli.peer_absent_threshold {
+            info!("peer {} is back on interface `{}`; monitoring it again ...",
+                self.cli.peer_key(self.cli.peer.as_deref().unwrap_or_default()), self.interface);
+        }
+
+        self.peer_absent_checks = 0;
+    }
+
+    /// Note that `--peer` was missing, and warn loudly if it has been for a while.
+    ///
+    /// Returns whether to fall back to reading the interface as a whole.
+    fn peer_absent(&mut self) -> bool {
+        let cli = self.cli;
+        let peer = cli.peer_key(cli.peer.as_deref().unwrap_or_default());
+        let threshold = cli.peer_absent_threshold;
+
+        self.peer_absent_checks += 1;
+        let checks = self.peer_absent_checks;
+
+        if checks < threshold {
+            warn!("peer {peer} not found on interface `{}` ({checks}/{threshold}); skipping ...", self.interface);
+            return false;
+        }
+
+        let fallback = cli.on_peer_absent == OnPeerAbsent::Fallback;
+
+        // Repeat the warning every `threshold` checks, unless it was dealt with by falling back
+        if checks == threshold || (!fallback && checks.is_multiple_of(threshold)) {
+            error!("peer {peer} has been missing from interface `{}` for {checks} consecutive checks; \
+                its public key may have changed when the interface was reconfigured. check --peer!", self.interface);
+
+            if fallback {
+                warn!("falling back to monitoring interface `{}` as if --peer was not given ...", self.interface);
+            } else {
+                error!("nothing is being monitored on interface `{}` until the peer reappears", self.interface);
+            }
+        }
+
+        fallback
+    }
+
+    /// Build a notification about the outcome of a remediation.
+    fn event<'e>(&'e self, elapsed: Option<time::Duration>, outcome: &'e str) -> notify::Event<'e> {
+        notify::Event {
+            interface: self.interface,
+            unit: &self.unit_name,
+            elapsed,
+            timeout: self.cli.timeout,
+            outcome,
+        }
+    }
+
+    /// Notify about an event, unless `--notify-cooldown` suppresses it.
+    fn notify(&mut self, elapsed: Option<time::Duration>, outcome: &str) {
+        if let Some(cooldown) = self.cli.notify_cooldown {
+            self.flush_notify_cooldown();
+
+            if !self.notify_cooldown.admit(outcome, cooldown, time::Instant::now()) {
+                info!("not notifying about `{outcome}` again within --notify-cooldown {}s ...", cooldown.as_secs());
+                return;
+            }
+        }
+
+        match self.aggregator {
+            Some(aggregator) if aggregate::AGGREGATED.contains(&outcome) => aggregator.push(&self.event(elapsed, outcome)),
+            _ => notify(self.cli, self.webhook, &self.event(elapsed, outcome)),
+        }
+    }
+
+    /// Send a summary of the notifications suppressed by `--notify-cooldown` for event types whose cooldown has passed.
+    fn flush_notify_cooldown(&mut self) {
+        let Some(cooldown) = self.cli.notify_cooldown else { return };
+
+        for (outcome, suppressed) in self.notify_cooldown.expired(cooldown, time::Instant::now()) {
+            let summary = format!("{suppressed} more `{outcome}` suppressed in the last {}", humantime::format_duration(cooldown));
+            notify(self.cli, self.webhook, &self.event(None, &summary));
+        }
+    }
+
+    /// Restart the unit, or reboot if restarts have not been helping,
+    /// unless the rate limit forbids it.
+    fn remediate(&mut self, elapsed: Option<time::Duration>) -> LoopDecision {
+        let cli = self.cli;
+        self.stale = Some(elapsed);
+
+        if self.low_uptime() {
+            return LoopDecision::LowUptime;
+        }
+
+        // Coalesce restart decisions made before the last restart had a chance to show
+        if let Some(since) = self.restart_in_flight {
+            if since.elapsed() < cli.restart_in_flight {
+                info!("restarted {}s ago without effect yet; not restarting again for up to {}s ...",
+                    since.elapsed().as_secs(), cli.restart_in_flight.as_secs());
+                return LoopDecision::RestartInFlight;
+            }
+
+            self.restart_in_flight = None;
+        }
+
+        // Keyed off system uptime rather than our own, so that being started mid-boot still defers
+        if let Some(window) = cli.post_boot_window {
+            match get_system_uptime(self.runner) {
+                Ok(uptime) if uptime < window => {
+                    info!("system booted {}s ago, within post-boot window of {}s; not restarting ...",
+                        uptime.as_secs(), window.as_secs());
+                    return LoopDecision::PostBoot;
+                },
+                Ok(_) => {},
+                Err(e) => warn!("{e}; ignoring post-boot window ..."),
+            }
+        }
+
+        if let Some(limit) = &cli.max_restarts_per
+            && !self.restart_history.allows(limit, time::Instant::now())
+        {
+            warn!("rate limit reached; {} restarts within the last {}. not restarting ...",
+                limit.count, humantime::format_duration(limit.window));
+            return LoopDecision::InCooldown;
+        }
 
-    /// Retry interval after unit restart in seconds
-    #[arg(short = 'r', long, value_parser = humantime::parse_duration, default_value = "30s")]
-    retry_after_unit_restart: time::Duration,
+        if let Some(decision) = self.check_breaker(elapsed) {
+            return decision;
+        }
 
-    /// WireGuard interface to monitor
-    interface: Option<String>,
-}
+        if cli.defer_if_active && self.defer() {
+            return LoopDecision::Deferred;
+        }
 
-/// Parse the first peer's latest-handshake timestamp from `wg show` output.
-fn first_peer_handshake_ts(output: &str) -> Option<u64> {
-    // Handshakes are in the tab-separated format "HASH\t1234567890\n"
-    let line = output.lines().next()?;  // first peer only
-    let (_, post) = line.split_once('\t')?;
+        if let Some(dir) = &cli.dump_on_timeout {
+            match dump::write(self.runner, dir, self.interface, cli.dump_keep as usize) {
+                Ok(path) => info!("wrote diagnostic snapshot to `{}`", path.display()),
+                Err(e) => error!("failed to write diagnostic snapshot to `{}`: {e}", dir.display()),
+            }
+        }
 
-    post
-        .trim()
-        .parse()
-        .ok()
-}
+        if let Some(reboot_after) = cli.reboot_after
+            && self.unrecovered_restarts >= reboot_after
+        {
+            match get_system_uptime(self.runner) {
+                Ok(uptime) if uptime >= cli.reboot_min_uptime => return self.reboot(elapsed),
+                Ok(uptime) => {
+                    warn!("{} restarts without recovery, but system uptime {}s < {}s; not rebooting ...",
+                        self.unrecovered_restarts, uptime.as_secs(), cli.reboot_min_uptime.as_secs());
+                },
+                Err(e) => {
+                    error!("{e}; not rebooting ...");
+                }
+            }
+        }
 
-/// Convert a UNIX timestamp (seconds since epoch) to SystemTime.
-fn unix_ts_to_system_time(ts: u64) -> time::SystemTime {
-    time::UNIX_EPOCH + time::Duration::from_secs(ts)
-}
+        self.restart(elapsed)
+    }
 
-/// Check if a systemd unit is active.
-fn get_systemd_unit_is_active(unit_name: &str) -> Result<bool, String> {
-    match process::Command::new("systemctl")
-        .args(["is-active", "-q", unit_name])
-        .status()
-    {
-        Ok(status) if status.success() => Ok(true),
-        Ok(_) => Ok(false),
-        Err(e) => Err(format!("failed to run `systemctl`: {e}"))
+    /// Whether the system has not been up for `--min-uptime-before-acting`, or its uptime cannot be read.
+    fn low_uptime(&self) -> bool {
+        let Some(min_uptime) = self.cli.min_uptime_before_acting else { return false };
+
+        match get_system_uptime(self.runner) {
+            Ok(uptime) if uptime < min_uptime => {
+                warn!("system uptime {}s < --min-uptime-before-acting {}s; deferring restart while the host may be unstable ...",
+                    uptime.as_secs(), min_uptime.as_secs());
+                true
+            },
+            Ok(_) => false,
+            Err(e) => {
+                error!("{e}; not restarting without knowing the system uptime ...");
+                true
+            },
+        }
+    }
+
+    /// How long to wait after a restart, as a multiple of the keepalive with `--retry-keepalive-multiple`.
+    ///
+    /// The keepalive is read anew every time, so the wait follows it if the
+    /// interface is reconfigured while monitoring; a change is logged once.
+    fn retry_after_restart(&mut self) -> time::Duration {
+        let cli = self.cli;
+        let Some(multiple) = cli.retry_keepalive_multiple else { return cli.retry_after_unit_restart };
+
+        let keepalive = get_keepalive(self.runner, self.interface, cli.peer.as_deref());
+
+        if let Ok(keepalive) = keepalive
+            && let Some(previous) = self.keepalive.replace(keepalive)
+            && previous != keepalive
+        {
+            let describe = |keepalive: Option<time::Duration>| keepalive.map_or("off".to_string(), |k| format!("{}s", k.as_secs()));
+            let retry_after = keepalive.map_or(cli.retry_after_unit_restart, |k| k * multiple);
+
+            info!("persistent keepalive of `{}` changed from {} to {}; retrying after {}s after restarts from now on ...",
+                self.interface, describe(previous), describe(keepalive), retry_after.as_secs());
+        }
+
+        match keepalive {
+            Ok(Some(keepalive)) => {
+                let retry_after = keepalive * multiple;
+                debug!("retrying after {}s, {multiple} times the keepalive of {}s ...", retry_after.as_secs(), keepalive.as_secs());
+                retry_after
+            },
+            Ok(None) => {
+                debug!("no persistent keepalive configured; retrying after {}s ...", cli.retry_after_unit_restart.as_secs());
+                cli.retry_after_unit_restart
+            },
+            Err(e) => {
+                warn!("{e}; retrying after {}s ...", cli.retry_after_unit_restart.as_secs());
+                cli.retry_after_unit_restart
+            },
+        }
+    }
+
+    /// Whether to hold off a restart because the tunnel is carrying traffic, for `--defer-if-active`.
+    fn defer(&mut self) -> bool {
+        let window = self.cli.defer_window;
+        let since = *self.deferring_since.get_or_insert_with(time::Instant::now);
+
+        if since.elapsed() >= window {
+            info!("restart deferred for {}s already; restarting despite any traffic ...", since.elapsed().as_secs());
+            return false;
+        }
+
+        let peer = self.cli.peer.as_deref();
+        let before = get_transfer_bytes(self.runner, self.interface, peer);
+        thread::sleep(TRAFFIC_SAMPLE);
+        let after = get_transfer_bytes(self.runner, self.interface, peer);
+
+        match (before, after) {
+            (Ok(before), Ok(after)) if after != before => {
+                info!("tunnel carried {} bytes in {}s; deferring restart for up to another {}s ...",
+                    after.abs_diff(before), TRAFFIC_SAMPLE.as_secs(), window.saturating_sub(since.elapsed()).as_secs());
+                true
+            },
+            (Ok(_), Ok(_)) => {
+                debug!("tunnel carried no traffic in {}s; restarting ...", TRAFFIC_SAMPLE.as_secs());
+                false
+            },
+            (Err(e), _) | (_, Err(e)) => {
+                warn!("{e}; not deferring restart ...");
+                false
+            },
+        }
+    }
+
+    /// Advance the circuit breaker, returning a decision if it forbids restarting.
+    fn check_breaker(&mut self, elapsed: Option<time::Duration>) -> Option<LoopDecision> {
+        let cli = self.cli;
+        let breaker_after = cli.breaker_after?;
+
+        match self.breaker {
+            BreakerState::Closed if self.unrecovered_restarts >= breaker_after => Some(self.open_breaker(cli.breaker_cooldown, elapsed)),
+            BreakerState::Closed => None,
+            BreakerState::Open { until, .. } if time::Instant::now() < until => {
+                info!("circuit breaker open; not restarting for another {}s ...", (until - time::Instant::now()).as_secs());
+                Some(LoopDecision::BreakerOpen)
+            },
+            BreakerState::Open { cooldown, .. } => {
+                info!("circuit breaker cooldown passed; trying one restart ...");
+                self.breaker = BreakerState::HalfOpen { cooldown };
+                None
+            },
+            BreakerState::HalfOpen { cooldown } if cooldown >= cli.breaker_max_cooldown && let Some(action) = cli.giveup_action => {
+                Some(self.give_up(action, elapsed))
+            },
+            BreakerState::HalfOpen { cooldown } => {
+                // Still stale, so the trial restart did not help
+                Some(self.open_breaker((cooldown * 2).min(cli.breaker_max_cooldown), elapsed))
+            },
+            BreakerState::GaveUp => {
+                info!("gave up on restarting; waiting for the connection to recover on its own ...");
+                Some(LoopDecision::BreakerOpen)
+            },
+        }
+    }
+
+    /// Give up on restarting after a trial restart failed at the longest cooldown, for `--giveup-action`.
+    fn give_up(&mut self, action: GiveupAction, elapsed: Option<time::Duration>) -> LoopDecision {
+        self.breaker_trips += 1;
+
+        match action {
+            GiveupAction::Idle => {
+                error!("{} restarts without recovery, even at the longest cooldown; giving up on restarting ...", self.unrecovered_restarts);
+                self.breaker = BreakerState::GaveUp;
+                self.notify(elapsed, "gave up");
+                LoopDecision::BreakerOpen
+            },
+            GiveupAction::Exit => {
+                error!("{} restarts without recovery, even at the longest cooldown; giving up and exiting with code {GIVEUP_EXIT_CODE} ...",
+                    self.unrecovered_restarts);
+                self.notify(elapsed, "gave up");
+                LoopDecision::Exit(process::ExitCode::from(GIVEUP_EXIT_CODE))
+            },
+        }
+    }
+
+    /// Stop restarting for `cooldown`, alerting about it.
+    fn open_breaker(&mut self, cooldown: time::Duration, elapsed: Option<time::Duration>) -> LoopDecision {
+        error!("{} restarts without recovery; opening circuit breaker, not restarting for {} ...",
+            self.unrecovered_restarts, humantime::format_duration(cooldown));
+
+        self.breaker = BreakerState::Open { until: time::Instant::now() + cooldown, cooldown };
+        self.breaker_trips += 1;
+        self.notify(elapsed, "breaker open");
+        LoopDecision::BreakerOpen
+    }
+
+    /// Reboot the system.
+    fn reboot(&mut self, elapsed: Option<time::Duration>) -> LoopDecision {
+        let cli = self.cli;
+
+        error!("!!! {} restarts without recovery; REBOOTING THE SYSTEM !!!", self.unrecovered_restarts);
+        error!("--> {}", cli.reboot_command);
+
+        let outcome = match self.runner.shell(&cli.reboot_command, self.interface).status() {
+            Ok(status) if status.success() => "rebooting",
+            Ok(status) => {
+                error!("reboot failed with status {}", status.code().unwrap_or(-1));
+                "reboot failed"
+            },
+            Err(e) => {
+                error!("failed to execute `{}`: {e}", cli.reboot_command);
+                "reboot failed"
+            }
+        };
+
+        // The reboot was the last resort; with `--state-file` escalation starts over once the system is back
+        if outcome == "rebooting" {
+            self.unrecovered_restarts = 0;
+        }
+
+        self.reboots += 1;
+        self.notify(elapsed, outcome);
+        LoopDecision::Rebooted
+    }
+
+    /// Restart the unit, by `systemctl restart` or `--restart-command`.
+    fn restart(&mut self, elapsed: Option<time::Duration>) -> LoopDecision {
+        let cli = self.cli;
+
+        // Restarting again means the last restart did not help
+        if self.awaiting_effect {
+            self.judge_restart(false);
+        }
+
+        // Read while the interface is still up, as it may not be right after the restart
+        self.retry_after = self.retry_after_restart();
+
+        info!("--> {}", self.restart_description);
+        self.restart_history.record(time::Instant::now());
+        self.restarted_at = Some(time::Instant::now());
+        self.deferring_since = None;
+
+        // A reading shared from before the restart would only make it look like it did not help
+        forget_all_latest_handshakes();
+        self.unrecovered_restarts += 1;
+        self.waiting_since = time::Instant::now();
+        self.rx_progress = RxProgress::new();
+        self.endpoints = EndpointHistory::new();
+        self.past_warn_threshold = false;
+
+        let result = match (cli.restart_command.as_deref(), self.dbus) {
+            (Some(command_line), _) => match self.runner.shell(command_line, self.interface).spawn()
+                .and_then(|mut child| wait_bounded(&mut child, cli.restart_timeout))
+            {
+                Ok(Some(status)) if status.success() => Ok(()),
+                Ok(Some(status)) => Err(format!("restart failed with status {}", status.code().unwrap_or(-1))),
+                Ok(None) => Err(format!("`{}` did not finish within {}s; killed it", self.restart_description,
+                    cli.restart_timeout.unwrap_or_default().as_secs())),
+                Err(e) => Err(format!("failed to execute `{}`: {e}", self.restart_description)),
+            },
+            (None, dbus) => {
+                let signalled = cli.restart_signal.map(|signal| with_retries(|| match dbus {
+                    Some(manager) => manager.kill(&self.unit_name, signal.number),
+                    None => systemctl_kill(self.runner, &self.unit_name, signal, cli.restart_timeout),
+                }));
+
+                let restarted = match signalled {
+                    Some(Ok(())) => Ok(()),
+                    Some(Err(e)) => {
+                        warn!("{e}; restarting instead ...");
+                        self.restart_unit()
+                    },
+                    None => self.restart_unit(),
+                };
+
+                restarted.map_err(|e| e.to_string())
+            }
+        };
+
+        let succeeded = result.is_ok();
+        self.log_restart_result(result);
+
+        let (decision, outcome) = match succeeded {
+            true => (LoopDecision::Restarted, "restarted"),
+            false => (LoopDecision::RestartFailed, "restart failed"),
+        };
+
+        match decision {
+            LoopDecision::Restarted => {
+                self.restarts += 1;
+                self.restart_in_flight = Some(time::Instant::now());
+            },
+            _ => self.failed_restarts += 1,
+        }
+
+        self.notify(elapsed, outcome);
+
+        match decision {
+            LoopDecision::Restarted if cli.verify_after_restart => {
+                let decision = self.verify_restart(elapsed);
+                self.judge_restart(matches!(decision, LoopDecision::RestartVerified));
+                decision
+            },
+            LoopDecision::Restarted => {
+                self.awaiting_effect = true;
+                decision
+            },
+            decision => decision,
+        }
+    }
+
+    /// Count whether the last successful restart led to recovery, and log how restarts have been doing overall.
+    fn judge_restart(&mut self, effective: bool) {
+        self.awaiting_effect = false;
+
+        match effective {
+            true => self.effective_restarts += 1,
+            false => self.ineffective_restarts += 1,
+        }
+
+        info!("restart effectiveness: {} of {} restarts led to recovery",
+            self.effective_restarts, self.effective_restarts + self.ineffective_restarts);
+    }
+
+    /// Log a failed restart, unless `--quiet-after` says it is one too many failing the same way.
+    ///
+    /// Past the first `--quiet-after` identical failures in a row, only every
+    /// as many further ones are logged, as a summary. A different failure, or
+    /// a successful restart, starts the count over.
+    fn log_restart_result(&mut self, result: Result<(), String>) {
+        let Some(restart_failures) = &mut self.restart_failures else {
+            if let Err(message) = result {
+                error!("{message}");
+            }
+
+            return;
+        };
+
+        let message = match result {
+            Ok(()) => {
+                if let Some(count) = restart_failures.succeeded() {
+                    info!("restart succeeded after failing {count} times in a row ...");
+                }
+
+                return;
+            },
+            Err(message) => message,
+        };
+
+        match restart_failures.failed(message, time::Instant::now()) {
+            failures::Report::Failure(message) => error!("{message}"),
+            failures::Report::GoingQuiet(message) => {
+                let quiet_after = self.cli.quiet_after.unwrap_or_default();
+                error!("{message}");
+                info!("restart failed the same way {quiet_after} times in a row; only logging a summary every {quiet_after} failures from now on ...");
+            },
+            failures::Report::Summary { message, count, over } => {
+                error!("restart still failing, {count} times in a row over {}: {message}", humantime::format_duration(over));
+            },
+            failures::Report::Quiet => {},
+        }
+    }
+
+    /// Restart the unit over D-Bus or with `systemctl`, retrying transient failures.
+    fn restart_unit(&self) -> Result<(), Error> {
+        let (verb, no_block, timeout) = (self.cli.systemctl_verb, self.cli.restart_no_block, self.cli.restart_timeout);
+
+        with_retries(|| match self.dbus {
+            Some(manager) if no_block => manager.restart_no_block(&self.unit_name, verb),
+            Some(manager) => manager.restart(&self.unit_name, verb, timeout),
+            None => systemctl_restart(self.runner, &self.unit_name, verb, no_block, timeout),
+        })
+    }
+
+    /// Poll for a handshake newer than the restart just done, for up to the retry interval, for `--verify-after-restart`.
+    ///
+    /// Without one the restart evidently did not help, so it is no longer
+    /// treated as in flight, and the next check escalates right away.
+    fn verify_restart(&mut self, elapsed: Option<time::Duration>) -> LoopDecision {
+        let restarted_at = time::SystemTime::now();
+        let started = time::Instant::now();
+
+        loop {
+            thread::sleep(VERIFY_POLL_INTERVAL.min(self.retry_after.saturating_sub(started.elapsed())));
+            forget_all_latest_handshakes();
+
+            match get_handshake_ts(self.cli, self.runner, self.interface, self.cli.peer.as_deref()) {
+                // Handshake timestamps only have whole seconds
+                Ok(Reading::Value(timestamp)) if timestamp != 0 && unix_ts_to_system_time(timestamp + 1) > restarted_at => {
+                    info!("fresh handshake {}s after restarting ...", started.elapsed().as_secs());
+                    return LoopDecision::RestartVerified;
+                },
+                Ok(_) => {},
+                Err(e) => debug!("{e}; still verifying restart ..."),
+            }
+
+            if started.elapsed() >= self.retry_after {
+                break;
+            }
+        }
+
+        error!("no fresh handshake within {}s of restarting; checking again at once ...", self.retry_after.as_secs());
+        self.restart_in_flight = None;
+        self.notify(elapsed, "restart unverified");
+        LoopDecision::RestartUnverified
     }
 }
 
+/// How often `--verify-after-restart` polls for a fresh handshake.
+const VERIFY_POLL_INTERVAL: time::Duration = time::Duration::from_secs(2);
+
 /// Main program entry point.
 fn main() -> process::ExitCode {
-    let cli = Cli::parse();
+    let mut args: Vec<_> = env::args_os().collect();
+
+    // Spliced in ahead of the command line, so that options given there take precedence
+    let credential = credential::name(&args).map(|name| (credential::load(&name), name));
+
+    if let Some((Ok(Some((_, options))), _)) = &credential {
+        args.splice(1..1, options.iter().map(Into::into));
+    }
+
+    let matches = Cli::command().get_matches_from(args);
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    cli.apply_profile(&matches);
+    let level = if cli.verbose { Some(log::LevelFilter::Debug) } else { cli.log_level };
 
-    let interface = match cli.interface.as_deref().map(str::trim) {
-        Some(s) if s.is_empty() => {
-            eprintln!("interface name cannot be empty; exiting ...");
+    match cli.log_file.as_deref().map(|path| logfile::RotatingFile::open(path, cli.log_max_size, cli.log_keep)).transpose() {
+        Ok(file) => init_logging(level, cli.journal, file),
+        Err(e) => {
+            init_logging(level, cli.journal, None);
+            error!("{e}; exiting ...");
             return process::ExitCode::FAILURE;
         },
-        Some(s) => s,
-        None => unreachable!(),  // should not happen due to clap's arg_required_else_help
+    }
+
+    match credential {
+        Some((Ok(Some((path, options))), _)) => debug!("read {} option(s) from credential `{}`", options.len(), path.display()),
+        Some((Ok(None), name)) => warn!("${} is not set; ignoring --credential `{name}` ...", credential::DIRECTORY_ENV_VAR),
+        Some((Err(e), _)) => {
+            error!("{e}; exiting ...");
+            return process::ExitCode::FAILURE;
+        },
+        None => {},
+    }
+
+    if cli.print_config {
+        print!("{}", output::record(cli.output_format, &config_record(&cli)));
+        return process::ExitCode::SUCCESS;
+    }
+
+    if cli.list_interfaces {
+        return list_interfaces(&cli);
+    }
+
+    if cli.backend == Backend::Dbus && cli.ssh.is_some() {
+        error!("--backend dbus only works locally and cannot be used with --ssh; exiting ...");
+        return process::ExitCode::FAILURE;
+    }
+
+    if !cli.ignore_interface.is_empty() && !cli.all && cli.interface_pattern().is_none() {
+        error!("--ignore-interface requires --all or an interface pattern; exiting ...");
+        return process::ExitCode::FAILURE;
+    }
+
+    if cli.aggregate_window.is_some() && !cli.all && cli.interface_pattern().is_none() {
+        error!("--aggregate-window requires --all or an interface pattern; exiting ...");
+        return process::ExitCode::FAILURE;
+    }
+
+    if let HandshakeSource::Replay(_) = cli.handshake_source
+        && (cli.all || cli.interface_pattern().is_some())
+    {
+        error!("--handshake-source replay:PATH only works with a single interface; exiting ...");
+        return process::ExitCode::FAILURE;
+    }
+
+    if let Some(pattern) = cli.interface_pattern() {
+        if cli.unit.is_some() || cli.peer.is_some() || cli.peer_name.is_some() {
+            error!("--unit, --peer and --peer-name cannot be used with an interface pattern; exiting ...");
+            return process::ExitCode::FAILURE;
+        }
+
+        match get_wg_interfaces(&CommandRunner::new(cli.ssh.clone())) {
+            Ok(interfaces) if interfaces.iter().any(|interface| glob::matches(pattern, interface)) => {},
+            Ok(interfaces) => {
+                error!("no interface matches `{pattern}` (found {}); exiting ...",
+                    if interfaces.is_empty() { "none".to_string() } else { interfaces.join(", ") });
+                return process::ExitCode::FAILURE;
+            },
+            Err(e) => {
+                error!("{e}; exiting ...");
+                return process::ExitCode::FAILURE;
+            },
+        }
+    }
+
+    // Resolve `--peer-name` to a key up front, so that everything else only has to know about `--peer`
+    let target_interface = match &cli.command {
+        Some(Command::Doctor { interface } | Command::Status { interface, .. } | Command::Show { interface, .. }) => Some(interface.as_str()),
+        None => cli.interface(),
+    };
+
+    if let Some(name) = &cli.peer_name
+        && let Some(interface) = target_interface.map(str::trim)
+    {
+        match get_peer_by_name(&cli, &CommandRunner::new(cli.ssh.clone()), interface, name) {
+            Ok(public_key) => {
+                info!("peer `{name}` has public key {}", cli.peer_key(&public_key));
+                cli.peer = Some(public_key);
+            },
+            Err(e) => {
+                error!("{e}; exiting ...");
+                return process::ExitCode::FAILURE;
+            }
+        }
+    }
+
+    match &cli.command {
+        Some(Command::Doctor { interface }) => return doctor::run(&cli, interface),
+        Some(Command::Status { interface, format }) => return status::run(&cli, interface, format),
+        Some(Command::Show { interface, json }) => return show::run(&cli, interface, *json),
+        None => {},
+    }
+
+    if let Some(threshold) = cli.warn_threshold
+        && threshold.resolve(cli.timeout) >= cli.timeout
+    {
+        error!("--warn-threshold must be below --timeout; exiting ...");
+        return process::ExitCode::FAILURE;
+    }
+
+    if let Some(hard_timeout) = cli.hard_timeout
+        && hard_timeout <= cli.timeout
+    {
+        error!("--hard-timeout must be above --timeout; exiting ...");
+        return process::ExitCode::FAILURE;
+    }
+
+    if let Some(margin) = cli.handshake_freshness_hysteresis
+        && margin >= cli.timeout
+    {
+        error!("--handshake-freshness-hysteresis must be below --timeout; exiting ...");
+        return process::ExitCode::FAILURE;
+    }
+
+    if cli.stdin_driver {
+        return driver::run(&cli);
+    }
+
+    // Read secrets up front, so an unreadable file is reported at startup rather than on the first notification
+    let webhook = match cli.notify_webhook.is_empty() {
+        false => match Secret::load(cli.webhook_token_file.as_deref(), webhook::TOKEN_ENV_VAR) {
+            Ok(token) => Some(Webhook::new(cli.notify_webhook.clone(), token, cli.webhook_timeout, http::Retry {
+                retries: cli.http_retries,
+                backoff: cli.http_retry_backoff,
+            })),
+            Err(e) => {
+                error!("{e}; exiting ...");
+                return process::ExitCode::FAILURE;
+            }
+        },
+        true => None,
     };
 
-    let unit_name = format!("wg-quick@{interface}.service");
+    if cli.all || cli.interface_pattern().is_some() {
+        // One `wg show all` per loop rather than one `wg show <interface>` per interface, unless sampling needs readings apart
+        if let HandshakeSource::Wg = cli.handshake_source
+            && cli.sample_count == 1
+        {
+            cli.handshake_source = HandshakeSource::WgAll { max_age: cli.loop_interval / 2 };
+        }
+
+        if cli.check_config {
+            return doctor::check_config(&cli, None);
+        }
+
+        if cli.startup_json {
+            print_startup_json(&cli, None);
+        }
+
+        return monitor_all(&cli, webhook.as_ref());
+    }
 
-    match get_systemd_unit_is_active(&unit_name) {
-        Ok(true) => {},
-        Ok(false) => {
-            eprintln!("systemd service `{unit_name}` is not active; exiting ...");
+    let interface = match cli.interface().map(str::trim) {
+        Some("") => {
+            error!("interface name cannot be empty; exiting ...");
             return process::ExitCode::FAILURE;
         },
-        Err(e) => {
-            eprintln!("failed to run `systemctl is-active`: {e}");
+        Some(s) => s,
+        None => {
+            error!("no interface given; exiting ...");
             return process::ExitCode::FAILURE;
         }
     };
 
-    // Everything looks good
-    println!("monitoring wireguard interface `{interface}` with systemd unit `{unit_name}` ...");
+    if cli.check_config {
+        return doctor::check_config(&cli, Some(interface));
+    }
 
-    // Main loop start
-    loop {
-        // Get latest-handshakes output from `wg show`
-        let wg_show = match process::Command::new("wg")
-            .args(["show", &interface, "latest-handshakes"])
-            .output()
-        {
-            Ok(output) => output,
+    if cli.startup_json {
+        print_startup_json(&cli, Some(interface));
+    }
+
+    let stop = sync::atomic::AtomicBool::new(false);
+    let monitor_thread = thread::Builder::new().name(interface.to_string());
+
+    thread::scope(|scope| {
+        let handle = match monitor_thread.spawn_scoped(scope, || monitor(&cli, webhook.as_ref(), None, interface, &stop)) {
+            Ok(handle) => handle,
             Err(e) => {
-                eprintln!("failed to run `wg show`: {e}");
-                thread::sleep(cli.loop_interval);
-                continue;
+                error!("failed to spawn monitoring thread: {e}");
+                return process::ExitCode::FAILURE;
             }
         };
 
-        if !wg_show.status.success() {
-            eprintln!("`wg show` returned {}: {}",
-                wg_show.status.code().expect("`wg show` status code error"),
-                String::from_utf8_lossy(&wg_show.stderr).trim());
-            thread::sleep(cli.loop_interval);
-            continue;
+        handle
+            .join()
+            .unwrap_or(process::ExitCode::FAILURE)  // panicked
+    })
+}
+
+/// Guards `READY=1`, which with `--all` would otherwise be sent again by every interface.
+static READY: sync::Once = sync::Once::new();
+
+/// Tell systemd that startup checks passed, when run as a `Type=notify` service.
+///
+/// With `--all` or an interface pattern this is done before any interface is
+/// found, as there may be none for a while; with a single interface, once its
+/// unit and `--up-check-command` have been checked.
+fn notify_ready() {
+    READY.call_once(|| {
+        if let Err(e) = sd_notify::send("READY=1") {
+            warn!("failed to notify systemd of readiness: {e}");
         }
+    });
+}
+
+/// Monitor every interface on the host, or every one matching the interface pattern, picking up new ones and dropping removed ones every `--rescan-interval`.
+///
+/// Each interface is monitored in a thread of its own, which is told to stop
+/// through its flag once the interface is no longer listed.
+fn monitor_all(cli: &Cli, webhook: Option<&Webhook>) -> process::ExitCode {
+    let runner = CommandRunner::new(cli.ssh.clone());
+    let mut monitored: collections::BTreeMap<String, sync::Arc<sync::atomic::AtomicBool>> = collections::BTreeMap::new();
+
+    // Ignored interfaces last seen, to log each only when it first shows up
+    let mut ignored = collections::BTreeSet::new();
+
+    let aggregator = cli.aggregate_window.map(Aggregator::new);
+    let aggregator = aggregator.as_ref();
+    let aggregator_stop = sync::atomic::AtomicBool::new(false);
+
+    // Nothing left to validate; finding interfaces is part of monitoring
+    notify_ready();
+
+    thread::scope(|scope| {
+        let mut threads: Vec<thread::ScopedJoinHandle<process::ExitCode>> = Vec::new();
+
+        if let Some(aggregator) = aggregator {
+            scope.spawn(|| aggregator.run(&aggregator_stop, |event| notify(cli, webhook, event)));
+        }
+
+        let exit_code = loop {
+            // One interface giving up ends monitoring of all of them, so that a supervisor can take over
+            let (finished, running): (Vec<_>, Vec<_>) = threads.drain(..).partition(|handle| handle.is_finished());
+            threads = running;
+
+            if finished.into_iter().any(|handle| handle.join().is_ok_and(|code| code == process::ExitCode::from(GIVEUP_EXIT_CODE))) {
+                break process::ExitCode::from(GIVEUP_EXIT_CODE);
+            }
+
+            let mut interfaces = match get_wg_interfaces(&runner) {
+                Ok(interfaces) => interfaces,
+                Err(e) => {
+                    error!("{e}");
+
+                    if cli.fail_fast {
+                        error!("exiting due to --fail-fast ...");
+                        break process::ExitCode::FAILURE;
+                    }
+
+                    thread::sleep(cli.rescan_interval);
+                    continue;
+                }
+            };
+
+            interfaces.retain(|interface| cli.interface_pattern().is_none_or(|pattern| glob::matches(pattern, interface)));
+            let mut now_ignored = collections::BTreeSet::new();
+
+            interfaces.retain(|interface| {
+                let Some(pattern) = cli.ignore_interface.iter().find(|pattern| glob::matches(pattern, interface)) else {
+                    return true;
+                };
 
-        let stdout = String::from_utf8_lossy(&wg_show.stdout);  // no need to .trim()
+                if !ignored.contains(interface) {
+                    info!("ignoring interface `{interface}`, which matches --ignore-interface `{pattern}` ...");
+                }
 
-        let timestamp = match first_peer_handshake_ts(&stdout) {
-            Some(v) => v,
-            None => {
-                eprintln!("unexpected `wg show latest-handshakes` output:\n{stdout}");
-                thread::sleep(cli.loop_interval);
-                continue;
+                now_ignored.insert(interface.clone());
+                false
+            });
+
+            ignored = now_ignored;
+
+            monitored.retain(|interface, stop| {
+                let present = interfaces.contains(interface);
+
+                if !present {
+                    info!("interface `{interface}` went away; no longer monitoring it ...");
+                    stop.store(true, sync::atomic::Ordering::Relaxed);
+                }
+
+                present
+            });
+
+            for interface in interfaces {
+                if monitored.contains_key(&interface) {
+                    continue;
+                }
+
+                info!("found interface `{interface}`; monitoring it ...");
+                let stop = sync::Arc::new(sync::atomic::AtomicBool::new(false));
+                let thread_stop = sync::Arc::clone(&stop);
+                let thread_interface = interface.clone();
+
+                // A thread that exits on its own stays in the map, so it is not respawned until its interface comes back
+                match thread::Builder::new()
+                    .name(interface.clone())
+                    .spawn_scoped(scope, move || monitor(cli, webhook, aggregator, &thread_interface, &thread_stop))
+                {
+                    Ok(handle) => {
+                        monitored.insert(interface, stop);
+                        threads.push(handle);
+                    },
+                    Err(e) => error!("failed to spawn monitoring thread for `{interface}`: {e}"),
+                }
+            }
+
+            if monitored.is_empty() {
+                debug!("no wireguard interfaces found; checking again in {}s ...", cli.rescan_interval.as_secs());
             }
+
+            thread::sleep(cli.rescan_interval);
         };
 
-        if timestamp == 0 {
-            eprintln!("no handshake recorded yet; waiting ...");
-            thread::sleep(cli.loop_interval);
-            continue;
+        for stop in monitored.values() {
+            stop.store(true, sync::atomic::Ordering::Relaxed);
         }
 
-        let last = unix_ts_to_system_time(timestamp);
-        let elapsed = time::SystemTime::now()
-            .duration_since(last)
-            .unwrap_or_default();
-
-        if elapsed <= cli.timeout {
-            thread::sleep(cli.loop_interval);
-            continue;
+        // What threads on their way out raise still has to be aggregated and sent
+        for handle in threads {
+            let _ = handle.join();
         }
 
-        eprintln!("handshake timeout; {}s > {}s. restarting service ...", elapsed.as_secs(), cli.timeout.as_secs());
-        eprintln!("--> systemctl restart {unit_name}");
+        aggregator_stop.store(true, sync::atomic::Ordering::Relaxed);
+        exit_code
+    })
+}
 
-        let systemctl_restart = match process::Command::new("systemctl")
-            .args(["restart", &unit_name])
-            .status()
-        {
-            Ok(s) => s,
+/// Monitor one interface until told to exit, or until `stop` is set.
+fn monitor(cli: &Cli, webhook: Option<&Webhook>, aggregator: Option<&Aggregator>, interface: &str, stop: &sync::atomic::AtomicBool) -> process::ExitCode {
+    let runner = CommandRunner::new(cli.ssh.clone());
+    let unit_name = cli.unit_name(interface);
+
+    let dbus = match cli.backend {
+        Backend::Dbus => match dbus::Manager::connect() {
+            Ok(manager) => Some(manager),
             Err(e) => {
-                eprintln!("failed to execute systemctl restart: {e}");
-                thread::sleep(cli.loop_interval);
-                continue;
+                warn!("{e}; falling back to systemctl ...");
+                None
             }
-        };
+        },
+        Backend::Systemd | Backend::Script => None,
+    };
+
+    match cli.backend {
+        Backend::Systemd | Backend::Dbus => match get_systemd_unit_state(&runner, dbus.as_ref(), &unit_name) {
+            Ok(state) if state.is_active() => {},
+            Ok(state) => {
+                error!("systemd service `{unit_name}` is not active ({state}); exiting ...");
+                return process::ExitCode::FAILURE;
+            },
+            Err(e) => {
+                error!("{e}; exiting ...");
+                return process::ExitCode::FAILURE;
+            }
+        },
+        Backend::Script => {
+            let up_check_command = cli.up_check_command.as_deref().expect("clap requires --up-check-command");
+
+            match get_script_is_up(&runner, up_check_command, interface) {
+                Ok(true) => {},
+                Ok(false) => {
+                    error!("up-check command reports interface `{interface}` is not up; exiting ...");
+                    return process::ExitCode::FAILURE;
+                },
+                Err(e) => {
+                    error!("{e}");
+                    return process::ExitCode::FAILURE;
+                }
+            }
+        }
+    };
+
+    // What gets run to restart the connection, for display purposes
+    let restart_description = match (cli.restart_command.as_deref(), &dbus) {
+        (Some(command_line), _) => command_line.to_string(),
+        (None, Some(_)) if let Some(signal) = cli.restart_signal => format!("KillUnit {unit_name} with {signal} over D-Bus"),
+        (None, Some(_)) if cli.restart_no_block => format!("{} {unit_name} over D-Bus, without waiting", cli.systemctl_verb.dbus_method()),
+        (None, Some(_)) => format!("{} {unit_name} over D-Bus", cli.systemctl_verb.dbus_method()),
+        (None, None) if let Some(signal) = cli.restart_signal => format!("systemctl kill -s {} --kill-whom=main {unit_name}", signal.name),
+        (None, None) if cli.restart_no_block => format!("systemctl --no-block {} {unit_name}", cli.systemctl_verb.command()),
+        (None, None) => format!("systemctl {} {unit_name}", cli.systemctl_verb.command()),
+    };
 
-        if !systemctl_restart.success() {
-            eprintln!("restart failed with status {}", systemctl_restart.code().unwrap_or(-1));
+    // `systemctl restart` needs root or a polkit rule; without either it only fails once a restart is due
+    if !cli.skip_privilege_check && cli.backend != Backend::Script && cli.restart_command.is_none() {
+        match get_euid(&runner) {
+            Ok(0) => {},
+            Ok(uid) => warn!("running as uid {uid} {}; `systemctl restart` will likely be refused unless a polkit rule allows it \
+                (disable this check with --skip-privilege-check)", runner.location()),
+            Err(e) => warn!("could not check whether restarts are permitted: {e}"),
         }
+    }
 
-        thread::sleep(cli.retry_after_unit_restart);
+    // Everything looks good
+    match cli.backend {
+        Backend::Systemd | Backend::Dbus if dbus.is_some() => info!("monitoring wireguard interface `{interface}` with systemd unit `{unit_name}` over D-Bus ..."),
+        Backend::Systemd | Backend::Dbus => info!("monitoring wireguard interface `{interface}` with systemd unit `{unit_name}` {} ...", runner.location()),
+        Backend::Script => info!("monitoring wireguard interface `{interface}` with restart command `{restart_description}` {} ...", runner.location()),
     }
+
+    log_startup_summary(cli, &runner, interface);
+
+    notify_ready();
+
+    let mut monitor = Monitor::new(cli, &runner, dbus.as_ref(), webhook, interface, unit_name, restart_description);
+    monitor.aggregator = aggregator;
+    monitor.restore_state();
+
+    // Main loop start
+    let exit_code = loop {
+        if stop.load(sync::atomic::Ordering::Relaxed) {
+            break process::ExitCode::SUCCESS;
+        }
+
+        let decision = match monitor.check() {
+            LoopDecision::Stale { elapsed } => monitor.remediate(elapsed),
+            LoopDecision::Healthy | LoopDecision::NoHandshakeYet | LoopDecision::InGrace | LoopDecision::Recovering | LoopDecision::NearTimeout
+            | LoopDecision::InDeadband if monitor.simulate_timeout() =>
+            {
+                warn!("simulating a timeout due to --simulate-timeout; restarting ...");
+                monitor.remediate(None)
+            },
+            decision => decision,
+        };
+
+        debug!("loop decision: {}", decision.label());
+        monitor.save_state();
+        monitor.write_metrics(decision.label());
+
+        match &decision {
+            LoopDecision::Exit(code) => break *code,
+            LoopDecision::ShowError(e) => {
+                error!("{e}");
+
+                if cli.fail_fast {
+                    error!("exiting due to --fail-fast ...");
+                    break process::ExitCode::FAILURE;
+                }
+            },
+            _ => {},
+        }
+
+        if let HandshakeSource::Replay(replay) = &cli.handshake_source
+            && !replay.advance()
+        {
+            info!("no more handshakes to replay; exiting ...");
+            break process::ExitCode::SUCCESS;
+        }
+
+        thread::sleep(decision.sleep_duration(cli, monitor.retry_after));
+    };
+
+    monitor.write_metrics(LoopDecision::Exit(exit_code).label());
+    print!("{}", output::record(cli.output_format, &monitor.summary()));
+    exit_code
 }
@@ -0,0 +1,102 @@
+//! `--metrics-textfile`, which writes Prometheus metrics to a file for the
+//! node_exporter textfile collector to pick up.
+
+use crate::error::Error;
+use std::io::Write;
+use std::{collections, fs, path, sync, time};
+
+/// What is reported about one interface.
+pub struct Sample {
+    pub unit: String,
+    pub restarts: u32,
+    pub failed_restarts: u32,
+    pub effective_restarts: u32,
+    pub ineffective_restarts: u32,
+    pub reboots: u32,
+    pub recoveries: u32,
+    pub breaker_trips: u32,
+    pub warnings: u32,
+
+    /// What the last check decided, as a `LoopDecision` label, or `exit` once monitoring ended
+    pub decision: &'static str,
+    pub updated_at: time::SystemTime,
+}
+
+/// A metric reported once per interface.
+struct Metric {
+    name: &'static str,
+    help: &'static str,
+    kind: &'static str,
+    value: fn(&Sample) -> u64,
+}
+
+const METRICS: &[Metric] = &[
+    Metric { name: "wg_restarter_restarts_total", help: "Restarts that succeeded.", kind: "counter", value: |s| s.restarts.into() },
+    Metric { name: "wg_restarter_failed_restarts_total", help: "Restarts that failed or could not be run.", kind: "counter", value: |s| s.failed_restarts.into() },
+    Metric { name: "wg_restarter_effective_restarts_total", help: "Restarts after which the connection recovered.", kind: "counter", value: |s| s.effective_restarts.into() },
+    Metric { name: "wg_restarter_ineffective_restarts_total", help: "Restarts after which the connection did not recover.", kind: "counter", value: |s| s.ineffective_restarts.into() },
+    Metric { name: "wg_restarter_reboots_total", help: "Reboots attempted.", kind: "counter", value: |s| s.reboots.into() },
+    Metric { name: "wg_restarter_recoveries_total", help: "Times the connection recovered after being stale.", kind: "counter", value: |s| s.recoveries.into() },
+    Metric { name: "wg_restarter_breaker_trips_total", help: "Times the circuit breaker opened.", kind: "counter", value: |s| s.breaker_trips.into() },
+    Metric { name: "wg_restarter_warnings_total", help: "Early warnings past --warn-threshold.", kind: "counter", value: |s| s.warnings.into() },
+    Metric {
+        name: "wg_restarter_last_update_timestamp_seconds",
+        help: "When these metrics were last written, in seconds since the epoch.",
+        kind: "gauge",
+        value: |s| s.updated_at.duration_since(time::UNIX_EPOCH).map_or(0, |since_epoch| since_epoch.as_secs()),
+    },
+];
+
+/// Latest sample of every interface, as every update rewrites the whole file.
+static SAMPLES: sync::Mutex<collections::BTreeMap<String, Sample>> = sync::Mutex::new(collections::BTreeMap::new());
+
+/// Escape a label value for the text exposition format.
+fn escape(value: &str) -> String {
+    value.replace('\\', r"\\").replace('"', "\\\"").replace('\n', r"\n")
+}
+
+/// Render the samples of all interfaces in the text exposition format.
+fn render(samples: &collections::BTreeMap<String, Sample>) -> String {
+    let mut text = String::new();
+
+    for metric in METRICS {
+        text += &format!("# HELP {} {}\n# TYPE {} {}\n", metric.name, metric.help, metric.name, metric.kind);
+
+        for (interface, sample) in samples {
+            text += &format!("{}{{interface=\"{}\",unit=\"{}\"}} {}\n",
+                metric.name, escape(interface), escape(&sample.unit), (metric.value)(sample));
+        }
+    }
+
+    text += "# HELP wg_restarter_decision What the last check decided, as a label; always 1.\n";
+    text += "# TYPE wg_restarter_decision gauge\n";
+
+    for (interface, sample) in samples {
+        text += &format!("wg_restarter_decision{{interface=\"{}\",unit=\"{}\",decision=\"{}\"}} 1\n",
+            escape(interface), escape(&sample.unit), sample.decision);
+    }
+
+    text
+}
+
+/// Record `sample` as the latest of `interface` and rewrite `path`.
+///
+/// The file is written next to `path` first and then renamed over it, so the
+/// collector only ever sees a complete file. The temporary file does not end
+/// in `.prom`, so the collector ignores it in the meantime.
+pub fn update(path: &path::Path, interface: &str, sample: Sample) -> Result<(), Error> {
+    let mut samples = SAMPLES.lock().unwrap_or_else(sync::PoisonError::into_inner);
+    samples.insert(interface.to_string(), sample);
+
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let temporary = path::PathBuf::from(temporary);
+
+    fs::File::create(&temporary)
+        .and_then(|mut file| {
+            file.write_all(render(&samples).as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|()| fs::rename(&temporary, path))
+        .map_err(|source| Error::Write { path: path.to_path_buf(), source })
+}
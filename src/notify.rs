@@ -0,0 +1,119 @@
+//! Notification messages built from a user-provided template.
+
+use std::time;
+
+/// Placeholders that may appear in a template, without braces.
+const PLACEHOLDERS: &[&str] = &["interface", "unit", "elapsed", "timeout", "outcome", "timestamp"];
+
+/// Template used unless overridden with `--notify-template`.
+pub const DEFAULT_TEMPLATE: &str = "{timestamp} {interface}: {outcome} (stale for {elapsed}, timeout {timeout})";
+
+/// Something that happened which a notification is sent about.
+pub struct Event<'a> {
+    pub interface: &'a str,
+    pub unit: &'a str,
+
+    /// How long the connection had been stale, if known
+    pub elapsed: Option<time::Duration>,
+    pub timeout: time::Duration,
+
+    /// What was done about it, e.g. "restarted"
+    pub outcome: &'a str,
+}
+
+/// One piece of a parsed template.
+#[derive(Clone)]
+enum Piece {
+    Literal(String),
+    Placeholder(&'static str),
+}
+
+/// A message template with `{placeholder}` substitutions, validated up front
+/// so that mistakes surface at startup rather than when a notification is sent.
+///
+/// Literal braces are written as `{{` and `}}`.
+#[derive(Clone)]
+pub struct Template {
+    pieces: Vec<Piece>,
+}
+
+impl Template {
+    /// Parse a notification template, rejecting unknown placeholders and unbalanced braces.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        Self::parse_with(s, PLACEHOLDERS)
+    }
+
+    /// Parse a template in which only `placeholders` may appear.
+    pub fn parse_with(s: &str, placeholders: &'static [&'static str]) -> Result<Self, String> {
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                },
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                },
+                '{' => {
+                    let (name, rest) = chars
+                        .as_str()
+                        .split_once('}')
+                        .ok_or_else(|| format!("unclosed `{{` in template `{s}`"))?;
+
+                    let placeholder = placeholders
+                        .iter()
+                        .find(|&&p| p == name)
+                        .ok_or_else(|| format!("unknown placeholder `{{{name}}}` in template; expected one of {}",
+                            placeholders.iter().map(|p| format!("{{{p}}}")).collect::<Vec<_>>().join(", ")))?;
+
+                    if !literal.is_empty() {
+                        pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+                    }
+
+                    pieces.push(Piece::Placeholder(placeholder));
+                    chars = rest.chars();
+                },
+                '}' => return Err(format!("unmatched `}}` in template `{s}`; write `}}}}` for a literal brace")),
+                c => literal.push(c),
+            }
+        }
+
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(literal));
+        }
+
+        Ok(Self { pieces })
+    }
+
+    /// Fill in the template with the details of an event.
+    pub fn render(&self, event: &Event) -> String {
+        self.render_with(|placeholder| match placeholder {
+            "interface" => event.interface.to_string(),
+            "unit" => event.unit.to_string(),
+            "elapsed" => match event.elapsed {
+                Some(elapsed) => format!("{}s", elapsed.as_secs()),
+                None => "n/a".to_string(),
+            },
+            "timeout" => format!("{}s", event.timeout.as_secs()),
+            "outcome" => event.outcome.to_string(),
+            "timestamp" => humantime::format_rfc3339_seconds(time::SystemTime::now()).to_string(),
+            other => unreachable!("placeholder `{other}` passed validation but is not rendered"),
+        })
+    }
+
+    /// Fill in the template with the value of each placeholder.
+    pub fn render_with(&self, value: impl Fn(&str) -> String) -> String {
+        self.pieces
+            .iter()
+            .map(|piece| match piece {
+                Piece::Literal(s) => s.clone(),
+                Piece::Placeholder(placeholder) => value(placeholder),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,100 @@
+//! Formatting of introspection output (interface lists, configuration, exit
+//! summaries) as human-readable tables or as JSON or CSV for other tools.
+
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+
+/// Shape of introspection output.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Aligned columns for humans
+    Table,
+
+    /// JSON, one object per record
+    Json,
+
+    /// Comma-separated values with a header line
+    Csv,
+}
+
+/// One set of named fields, in display order.
+pub type Record = Map<String, Value>;
+
+/// Render a field value as plain text.
+fn text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Quote a CSV field if it needs it.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Render a list of records sharing the same fields, e.g. one per interface.
+pub fn records(format: OutputFormat, records: &[Record]) -> String {
+    let columns: Vec<&String> = records.first().map(|r| r.keys().collect()).unwrap_or_default();
+
+    match format {
+        OutputFormat::Json => {
+            let array = records.iter().cloned().map(Value::Object).collect();
+            format!("{}\n", Value::Array(array))
+        },
+        OutputFormat::Csv => {
+            let mut out = String::new();
+            let line = |fields: Vec<String>| fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(",") + "\n";
+
+            out += &line(columns.iter().map(|c| c.to_string()).collect());
+
+            for record in records {
+                out += &line(record.values().map(text).collect());
+            }
+
+            out
+        },
+        OutputFormat::Table => {
+            let cells: Vec<Vec<String>> = records.iter().map(|r| r.values().map(text).collect()).collect();
+            let widths: Vec<usize> = columns
+                .iter()
+                .enumerate()
+                .map(|(i, c)| cells.iter().map(|row| row[i].len()).chain([c.len()]).max().unwrap_or(0))
+                .collect();
+
+            let line = |fields: Vec<&str>| {
+                let padded: Vec<String> = fields.iter().zip(&widths).map(|(f, w)| format!("{f:<w$}")).collect();
+                padded.join("  ").trim_end().to_string() + "\n"
+            };
+
+            let mut out = line(columns.iter().map(|c| c.as_str()).collect());
+
+            for row in &cells {
+                out += &line(row.iter().map(String::as_str).collect());
+            }
+
+            out
+        }
+    }
+}
+
+/// Render a single record, e.g. the effective configuration.
+pub fn record(format: OutputFormat, record: &Record) -> String {
+    match format {
+        OutputFormat::Json => format!("{}\n", Value::Object(record.clone())),
+        OutputFormat::Csv => records(format, std::slice::from_ref(record)),
+        OutputFormat::Table => {
+            let width = record.keys().map(String::len).max().unwrap_or(0);
+
+            record
+                .iter()
+                .map(|(key, value)| format!("{key:<width$}  {}", text(value)).trim_end().to_string() + "\n")
+                .collect()
+        }
+    }
+}
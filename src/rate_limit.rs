@@ -0,0 +1,56 @@
+//! `--max-restarts-per`, a budget of restarts within a rolling time window.
+
+use crate::duration;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// A budget of restarts allowed within a rolling time window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimit {
+    pub count: usize,
+    pub window: Duration,
+}
+
+/// Parse a `COUNT/WINDOW` rate limit, e.g. `5/1h`.
+pub fn parse(s: &str) -> Result<RateLimit, String> {
+    let (count, window) = s
+        .split_once('/')
+        .ok_or_else(|| format!("expected COUNT/WINDOW, got `{s}`"))?;
+
+    let count = match count.trim().parse() {
+        Ok(0) => return Err("restart count must be at least 1".to_string()),
+        Ok(n) => n,
+        Err(e) => return Err(format!("invalid restart count `{count}`: {e}")),
+    };
+
+    let window = duration::parse(window)
+        .map_err(|e| format!("invalid window `{window}`: {e}"))?;
+
+    Ok(RateLimit { count, window })
+}
+
+/// Times of recent restarts, for enforcing a `RateLimit`.
+#[derive(Default)]
+pub struct RestartHistory {
+    restarts: VecDeque<Instant>,
+}
+
+impl RestartHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether another restart at `now` fits within the budget, forgetting restarts that aged out of the window.
+    pub fn allows(&mut self, limit: &RateLimit, now: Instant) -> bool {
+        while self.restarts.front().is_some_and(|&t| now.saturating_duration_since(t) > limit.window) {
+            self.restarts.pop_front();
+        }
+
+        self.restarts.len() < limit.count
+    }
+
+    /// Count a restart made at `now`.
+    pub fn record(&mut self, now: Instant) {
+        self.restarts.push_back(now);
+    }
+}
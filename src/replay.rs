@@ -0,0 +1,93 @@
+//! `--handshake-source replay:PATH`, for testing only: one handshake age per
+//! loop iteration, read from a file, so that a sequence of readings can be
+//! fed to the monitoring loop and the decisions it makes asserted on.
+
+use std::sync::atomic::{self, AtomicUsize};
+use std::{fs, path, time};
+
+/// Public key of the single peer every reading is presented as.
+pub const PEER: &str = "cmVwbGF5cmVwbGF5cmVwbGF5cmVwbGF5cmVwbGF5cmU=";
+
+/// The latest handshake at one step.
+enum Step {
+    /// There has been none
+    Never,
+
+    /// This many seconds before the step is read, or after it if negative, as after the clock jumped backwards
+    Age(i64),
+
+    /// At this Unix timestamp
+    At(u64),
+}
+
+/// Handshakes to present, and which one is current.
+pub struct Replay {
+    path: path::PathBuf,
+    steps: Vec<Step>,
+    step: AtomicUsize,
+}
+
+/// Parse one step: a number of seconds, possibly negative, `@` and a Unix timestamp, or `never`.
+fn parse_step(line: &str) -> Result<Step, String> {
+    let step = match line {
+        "never" => return Ok(Step::Never),
+        line => match line.strip_prefix('@') {
+            Some(timestamp) => timestamp.parse().map(Step::At),
+            None => line.parse().map(Step::Age),
+        },
+    };
+
+    step.map_err(|e| format!("invalid step `{line}`: {e}"))
+}
+
+impl Replay {
+    /// Read the steps from `path`, one per line; blank lines and lines starting with `#` are skipped.
+    pub fn load(path: &path::Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| format!("failed to read `{}`: {e}", path.display()))?;
+
+        let steps = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(parse_step)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if steps.is_empty() {
+            return Err(format!("no steps in `{}`", path.display()));
+        }
+
+        Ok(Self { path: path.to_path_buf(), steps, step: AtomicUsize::new(0) })
+    }
+
+    pub fn path(&self) -> &path::Path {
+        &self.path
+    }
+
+    /// The current step as latest-handshakes output, with its age measured from now.
+    pub fn output(&self) -> Vec<u8> {
+        let timestamp = match self.steps[self.step.load(atomic::Ordering::Relaxed)] {
+            Step::Age(age) => {
+                let now = time::SystemTime::now().duration_since(time::UNIX_EPOCH).map_or(0, |since_epoch| since_epoch.as_secs());
+
+                // Never 0, which would mean no handshake at all
+                now.saturating_add_signed(age.saturating_neg()).max(1)
+            },
+            Step::At(timestamp) => timestamp,
+            Step::Never => 0,
+        };
+
+        format!("{PEER}\t{timestamp}\n").into_bytes()
+    }
+
+    /// Move on to the next step, returning whether there was one.
+    pub fn advance(&self) -> bool {
+        let next = self.step.load(atomic::Ordering::Relaxed) + 1;
+
+        if next >= self.steps.len() {
+            return false;
+        }
+
+        self.step.store(next, atomic::Ordering::Relaxed);
+        true
+    }
+}
@@ -0,0 +1,76 @@
+//! Construction of the external commands that inspect and restart a
+//! connection, run either locally or on a remote host over SSH.
+
+use std::process;
+
+/// Builds commands to run locally, or remotely when given an SSH destination.
+pub struct CommandRunner {
+    /// `user@host` to run commands on, if not local
+    ssh: Option<String>,
+}
+
+impl CommandRunner {
+    pub fn new(ssh: Option<String>) -> Self {
+        Self { ssh }
+    }
+
+    /// Build a command running `program` with `args`.
+    ///
+    /// Remotely this becomes `ssh -o BatchMode=yes DESTINATION -- PROGRAM ARGS...`,
+    /// with every argument quoted for the remote shell. `BatchMode` makes SSH fail
+    /// instead of prompting for a password, so key-based authentication is required.
+    /// A connection failure surfaces as the command exiting with status 255.
+    pub fn command(&self, program: &str, args: &[&str]) -> process::Command {
+        match &self.ssh {
+            None => {
+                let mut command = process::Command::new(program);
+                command.args(args);
+                command
+            },
+            Some(destination) => {
+                let mut command = process::Command::new("ssh");
+                command
+                    .args(["-o", "BatchMode=yes", "-o", "ConnectTimeout=10", destination, "--"])
+                    .arg(quote(program))
+                    .args(args.iter().map(|arg| quote(arg)));
+                command
+            }
+        }
+    }
+
+    /// Name of the program actually spawned to run `program`, for error messages.
+    pub fn spawned<'p>(&self, program: &'p str) -> &'p str {
+        match self.ssh {
+            None => program,
+            Some(_) => "ssh",
+        }
+    }
+
+    /// Build a `sh -c` invocation of a user-provided command line.
+    ///
+    /// The interface name is exported to the command as `WG_INTERFACE`.
+    pub fn shell(&self, command_line: &str, interface: &str) -> process::Command {
+        let assignment = format!("WG_INTERFACE={interface}");
+        self.command("env", &[&assignment, "sh", "-c", command_line])
+    }
+
+    /// Describe where commands are run, for log messages.
+    pub fn location(&self) -> String {
+        match &self.ssh {
+            None => "locally".to_string(),
+            Some(destination) => format!("on `{destination}` over ssh"),
+        }
+    }
+}
+
+/// Quote an argument for a POSIX shell, leaving plainly safe ones as they are
+/// so that remote commands stay readable in `ps` and logs.
+fn quote(arg: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "-_./@:=%+,".contains(c);
+
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        return arg.to_string();
+    }
+
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
@@ -0,0 +1,26 @@
+//! Readiness notification for running as a `Type=notify` service.
+//!
+//! Like the journal's, the protocol is simple enough not to need
+//! `libsystemd`: one datagram of `VARIABLE=value` lines, sent to the socket
+//! systemd passes in `$NOTIFY_SOCKET`.
+
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::{ffi::OsStrExt, net};
+use std::{env, io};
+
+/// Environment variable systemd points at its notification socket.
+pub const SOCKET_ENV_VAR: &str = "NOTIFY_SOCKET";
+
+/// Send `state`, e.g. `READY=1`, if systemd is waiting for notifications.
+pub fn send(state: &str) -> io::Result<()> {
+    let Some(path) = env::var_os(SOCKET_ENV_VAR) else { return Ok(()) };
+    let socket = net::UnixDatagram::unbound()?;
+
+    // A leading `@` means a socket in the abstract namespace
+    let address = match path.as_bytes().strip_prefix(b"@") {
+        Some(name) => net::SocketAddr::from_abstract_name(name)?,
+        None => net::SocketAddr::from_pathname(&path)?,
+    };
+
+    socket.send_to_addr(state.as_bytes(), &address).map(|_| ())
+}
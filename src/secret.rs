@@ -0,0 +1,51 @@
+//! Sensitive values, such as notification credentials, read from a file or
+//! the environment so that they never appear on the command line.
+
+use crate::error::Error;
+use std::{env, fmt, fs, path};
+
+/// A value that is never displayed or logged.
+pub struct Secret(String);
+
+impl Secret {
+    /// Read a secret from `file` if given, otherwise from the environment variable `env_var`.
+    ///
+    /// Surrounding whitespace is trimmed, so files may end with a newline.
+    /// An empty secret is an error, as it is almost certainly a mistake.
+    pub fn load(file: Option<&path::Path>, env_var: &str) -> Result<Option<Self>, Error> {
+        let value = match file {
+            Some(file) => fs::read_to_string(file)
+                .map_err(|source| Error::Read { path: file.to_path_buf(), source })?,
+            None => match env::var(env_var) {
+                Ok(value) => value,
+                Err(env::VarError::NotPresent) => return Ok(None),
+                Err(source) => return Err(Error::Env { var: env_var.to_string(), source }),
+            },
+        };
+
+        match value.trim() {
+            "" => match file {
+                Some(file) => Err(Error::EmptySecret(format!("secret file `{}`", file.display()))),
+                None => Err(Error::EmptySecret(format!("`{env_var}`"))),
+            },
+            value => Ok(Some(Self(value.to_string()))),
+        }
+    }
+
+    /// The secret value itself, for passing on where it is needed.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("[redacted]")
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("[redacted]")
+    }
+}
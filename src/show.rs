@@ -0,0 +1,72 @@
+//! The `show` subcommand, which reads `wg show <interface> dump` once and
+//! prints the status of every peer, e.g. for a dashboard. It never restarts
+//! anything.
+
+use crate::output::{self, OutputFormat};
+use crate::runner::CommandRunner;
+use crate::Cli;
+use crate::error::Error;
+use log::error;
+use serde_json::Value;
+use std::{process, time};
+use wg_restarter::{clock, wg};
+
+/// Describe one peer, judging it healthy if it handshaked within `--timeout`.
+fn peer_record(cli: &Cli, now: time::SystemTime, peer: &wg::Peer<'_>) -> output::Record {
+    let handshaked = (peer.latest_handshake > 0).then_some(peer.latest_handshake);
+
+    // A handshake from the future has just happened, as far as can be told
+    let age = handshaked.map(|timestamp| clock::age(now, timestamp).unwrap_or_default());
+
+    let endpoint = match peer.endpoint {
+        "(none)" => Value::Null,
+        endpoint => endpoint.into(),
+    };
+
+    let mut record = output::Record::new();
+    record.insert("public_key".into(), cli.peer_key(peer.public_key).into());
+    record.insert("endpoint".into(), endpoint);
+    record.insert("latest_handshake".into(), handshaked
+        .map(|timestamp| humantime::format_rfc3339_seconds(crate::unix_ts_to_system_time(timestamp)).to_string().into())
+        .unwrap_or(Value::Null));
+    record.insert("age_secs".into(), age.map(|age| Value::from(age.as_secs())).unwrap_or(Value::Null));
+    record.insert("rx_bytes".into(), peer.rx_bytes.into());
+    record.insert("tx_bytes".into(), peer.tx_bytes.into());
+    record.insert("healthy".into(), age.is_some_and(|age| age <= cli.timeout).into());
+    record
+}
+
+/// Print the status of every peer of `interface`, or only of `--peer` if given, and exit.
+///
+/// With `json` the output is a JSON array regardless of `--output-format`.
+pub fn run(cli: &Cli, interface: &str, json: bool) -> process::ExitCode {
+    let runner = CommandRunner::new(cli.ssh.clone());
+
+    let stdout = match crate::get_wg_show(&runner, interface, "dump") {
+        Ok(stdout) => stdout,
+        Err(e) => {
+            error!("{e}; exiting ...");
+            return process::ExitCode::FAILURE;
+        },
+    };
+
+    let Some(peers) = wg::parse_dump(&stdout) else {
+        error!("{}; exiting ...", Error::parse("wg show dump", &stdout));
+        return process::ExitCode::FAILURE;
+    };
+
+    let now = time::SystemTime::now();
+    let records: Vec<output::Record> = peers
+        .iter()
+        .filter(|peer| cli.peer.as_deref().is_none_or(|public_key| peer.public_key == public_key))
+        .map(|peer| peer_record(cli, now, peer))
+        .collect();
+
+    let format = match json {
+        true => OutputFormat::Json,
+        false => cli.output_format,
+    };
+
+    print!("{}", output::records(format, &records));
+    process::ExitCode::SUCCESS
+}
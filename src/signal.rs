@@ -0,0 +1,36 @@
+//! Signals that can be sent with `--restart-signal`.
+
+use std::fmt;
+
+/// A signal, by name and number.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Signal {
+    /// Name without the `SIG` prefix, as `systemctl kill -s` takes it
+    pub name: &'static str,
+    pub number: i32,
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SIG{}", self.name)
+    }
+}
+
+/// Signals by name and number, as numbered on most Linux architectures.
+const SIGNALS: &[(&str, i32)] = &[
+    ("HUP", 1), ("INT", 2), ("QUIT", 3), ("ABRT", 6), ("KILL", 9), ("USR1", 10),
+    ("USR2", 12), ("ALRM", 14), ("TERM", 15), ("CONT", 18), ("STOP", 19), ("WINCH", 28),
+];
+
+/// Parse a signal name, with or without the `SIG` prefix, e.g. `HUP` or `SIGUSR1`.
+pub fn parse(s: &str) -> Result<Signal, String> {
+    let upper = s.trim().to_ascii_uppercase();
+    let name = upper.strip_prefix("SIG").unwrap_or(&upper);
+
+    SIGNALS
+        .iter()
+        .find(|&&(known, _)| known == name)
+        .map(|&(name, number)| Signal { name, number })
+        .ok_or_else(|| format!("unknown signal `{s}`; expected one of {}",
+            SIGNALS.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", ")))
+}
@@ -0,0 +1,20 @@
+//! Parsing of sizes given on the command line.
+
+/// Parse a size given either as a bare number of bytes (`1048576`) or with a
+/// binary suffix (`512K`, `10M`, `1G`).
+pub fn parse(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let (number, multiplier) = match s.char_indices().last() {
+        Some((i, 'K' | 'k')) => (&s[..i], 1 << 10),
+        Some((i, 'M' | 'm')) => (&s[..i], 1 << 20),
+        Some((i, 'G' | 'g')) => (&s[..i], 1 << 30),
+        _ => (s, 1),
+    };
+
+    number
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(|| format!("invalid size `{s}`; expected e.g. `1048576`, `512K` or `10M`"))
+}
@@ -0,0 +1,127 @@
+//! `--state-file`, which keeps what the monitor has learned about each
+//! interface's recent history across restarts of the monitor itself, so that
+//! escalation and the circuit breaker carry on where they left off.
+//!
+//! The file holds a JSON object with one entry per interface:
+//!
+//! ```json
+//! {
+//!   "wg0": {
+//!     "unrecovered_restarts": 2,
+//!     "breaker": { "state": "open", "until": 1760437000, "cooldown_secs": 1800 }
+//!   }
+//! }
+//! ```
+
+use crate::BreakerState;
+use crate::error::Error;
+use serde_json::{Map, Value, json};
+use std::{fs, io, path, sync, time};
+
+/// Held while reading and rewriting the file, which interfaces monitored at once share.
+static LOCK: sync::Mutex<()> = sync::Mutex::new(());
+
+/// What is kept of one interface.
+#[derive(PartialEq)]
+pub struct Saved {
+    /// Restarts in a row that did not bring back a fresh handshake
+    pub unrecovered_restarts: u32,
+    pub breaker: BreakerState,
+}
+
+fn unix_secs(time: time::SystemTime) -> u64 {
+    time.duration_since(time::UNIX_EPOCH).map_or(0, |since_epoch| since_epoch.as_secs())
+}
+
+fn from_unix_secs(secs: u64) -> time::SystemTime {
+    time::UNIX_EPOCH + time::Duration::from_secs(secs)
+}
+
+impl Saved {
+    fn to_json(&self) -> Value {
+        let breaker = match self.breaker {
+            BreakerState::Closed => json!({ "state": "closed" }),
+            BreakerState::Open { until, cooldown } => {
+                // Instants do not survive a restart, so store when the cooldown ends by the wall clock
+                let until = time::SystemTime::now() + until.saturating_duration_since(time::Instant::now());
+                json!({ "state": "open", "until": unix_secs(until), "cooldown_secs": cooldown.as_secs() })
+            },
+            BreakerState::HalfOpen { cooldown } => json!({ "state": "half_open", "cooldown_secs": cooldown.as_secs() }),
+            BreakerState::GaveUp => json!({ "state": "gave_up" }),
+        };
+
+        json!({
+            "unrecovered_restarts": self.unrecovered_restarts,
+            "breaker": breaker,
+        })
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        let breaker = &value["breaker"];
+        let cooldown = || breaker["cooldown_secs"].as_u64().map(time::Duration::from_secs);
+
+        let breaker = match breaker["state"].as_str()? {
+            "closed" => BreakerState::Closed,
+            "open" => {
+                let until = from_unix_secs(breaker["until"].as_u64()?);
+                let remaining = until.duration_since(time::SystemTime::now()).unwrap_or_default();
+                BreakerState::Open { until: time::Instant::now() + remaining, cooldown: cooldown()? }
+            },
+            "half_open" => BreakerState::HalfOpen { cooldown: cooldown()? },
+            "gave_up" => BreakerState::GaveUp,
+            _ => return None,
+        };
+
+        Some(Self {
+            unrecovered_restarts: value["unrecovered_restarts"].as_u64()?.try_into().ok()?,
+            breaker,
+        })
+    }
+}
+
+/// Read the whole file, or an empty object if there is none yet.
+fn read(path: &path::Path) -> Result<Map<String, Value>, Error> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Map::new()),
+        Err(source) => return Err(Error::Read { path: path.to_path_buf(), source }),
+    };
+
+    match serde_json::from_str(&contents) {
+        Ok(Value::Object(interfaces)) => Ok(interfaces),
+        Ok(_) => Err(Error::StateFile { path: path.to_path_buf(), reason: "expected a JSON object".to_string() }),
+        Err(e) => Err(Error::StateFile { path: path.to_path_buf(), reason: e.to_string() }),
+    }
+}
+
+/// Read what was saved about `interface`, if anything.
+pub fn load(path: &path::Path, interface: &str) -> Result<Option<Saved>, Error> {
+    let _lock = LOCK.lock().unwrap_or_else(sync::PoisonError::into_inner);
+
+    match read(path)?.get(interface) {
+        Some(value) => Saved::from_json(value)
+            .map(Some)
+            .ok_or_else(|| Error::StateFile { path: path.to_path_buf(), reason: format!("unexpected entry for `{interface}`") }),
+        None => Ok(None),
+    }
+}
+
+/// Save `saved` as what is known about `interface`, leaving other interfaces as they are.
+///
+/// The file is replaced in one step, so that it is never left half written.
+pub fn save(path: &path::Path, interface: &str, saved: &Saved) -> Result<(), Error> {
+    let _lock = LOCK.lock().unwrap_or_else(sync::PoisonError::into_inner);
+
+    let mut interfaces = read(path)?;
+    interfaces.insert(interface.to_string(), saved.to_json());
+
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let temporary = path::PathBuf::from(temporary);
+
+    let contents = serde_json::to_string_pretty(&Value::Object(interfaces)).expect("JSON values always serialize");
+
+    fs::write(&temporary, contents + "\n")
+        .and_then(|()| fs::rename(&temporary, path))
+        .map_err(|source| Error::Write { path: path.to_path_buf(), source })
+}
@@ -0,0 +1,97 @@
+//! The `status` subcommand, which reads the handshake once and prints a
+//! one-line summary, e.g. for a status bar. It never restarts anything.
+
+use crate::notify::Template;
+use crate::runner::CommandRunner;
+use crate::{Cli, Reading};
+use log::error;
+use std::{process, time};
+use wg_restarter::clock;
+
+/// Placeholders that may appear in a `--format`, without braces.
+const PLACEHOLDERS: &[&str] = &["interface", "symbol", "age", "state"];
+
+/// Format used unless overridden with `--format`.
+pub const DEFAULT_FORMAT: &str = "{interface} {symbol} {age}";
+
+/// Parse a `--format`, rejecting unknown placeholders.
+pub fn parse_format(s: &str) -> Result<Template, String> {
+    Template::parse_with(s, PLACEHOLDERS)
+}
+
+/// What the single reading says about the connection.
+enum State {
+    Fresh(time::Duration),
+    Stale(time::Duration),
+    NoHandshakeYet,
+    PeerAbsent,
+    NoPeers,
+    Error,
+}
+
+impl State {
+    fn symbol(&self) -> &'static str {
+        match self {
+            Self::Fresh(_) => "✓",
+            Self::Stale(_) => "✗",
+            Self::NoHandshakeYet | Self::PeerAbsent | Self::NoPeers => "?",
+            Self::Error => "!",
+        }
+    }
+
+    fn age(&self) -> String {
+        match self {
+            Self::Fresh(age) | Self::Stale(age) => format!("{}s", age.as_secs()),
+            Self::NoHandshakeYet => "never".to_string(),
+            Self::PeerAbsent | Self::NoPeers | Self::Error => "n/a".to_string(),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Fresh(_) => "fresh",
+            Self::Stale(_) => "stale",
+            Self::NoHandshakeYet => "no_handshake_yet",
+            Self::PeerAbsent => "peer_absent",
+            Self::NoPeers => "no_peers",
+            Self::Error => "error",
+        }
+    }
+}
+
+/// Read the handshake of `interface` once, print a line per `format`, and exit successfully only if it is fresh.
+pub fn run(cli: &Cli, interface: &str, format: &Template) -> process::ExitCode {
+    let runner = CommandRunner::new(cli.ssh.clone());
+
+    let state = match crate::get_handshake_ts(cli, &runner, interface, cli.peer.as_deref()) {
+        Ok(Reading::Value(0)) => State::NoHandshakeYet,
+        Ok(Reading::Value(timestamp)) => {
+            // A handshake from the future has just happened, as far as can be told
+            let age = clock::age(time::SystemTime::now(), timestamp).unwrap_or_default();
+
+            match age <= cli.timeout {
+                true => State::Fresh(age),
+                false => State::Stale(age),
+            }
+        },
+        Ok(Reading::PeerAbsent) => State::PeerAbsent,
+        Ok(Reading::NoPeers) => State::NoPeers,
+        Err(e) => {
+            error!("{e}");
+            State::Error
+        },
+    };
+
+    println!("{}", format.render_with(|placeholder| match placeholder {
+        "interface" => interface.to_string(),
+        "symbol" => state.symbol().to_string(),
+        "age" => state.age(),
+        "state" => state.name().to_string(),
+        other => unreachable!("placeholder `{other}` passed validation but is not rendered"),
+    }));
+
+    match state {
+        State::Fresh(_) => process::ExitCode::SUCCESS,
+        _ => process::ExitCode::FAILURE,
+    }
+}
@@ -0,0 +1,38 @@
+//! Thresholds of staleness given either as a duration or relative to the
+//! timeout, as for `--warn-threshold` and `--overshoot-deadband`.
+
+use crate::duration;
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+/// A duration of staleness, either absolute or a percentage of the timeout.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Threshold {
+    Absolute(Duration),
+    Percent(u32),
+}
+
+impl Threshold {
+    /// The threshold as a duration, given the restart timeout.
+    pub fn resolve(self, timeout: Duration) -> Duration {
+        match self {
+            Self::Absolute(threshold) => threshold,
+            Self::Percent(percent) => timeout * percent / 100,
+        }
+    }
+}
+
+/// Parse a threshold, either a `DURATION` or a percentage within `percents`, like `70%`.
+pub fn parse(s: &str, percents: RangeInclusive<u32>) -> Result<Threshold, String> {
+    let Some(percent) = s.trim().strip_suffix('%') else {
+        return duration::parse(s)
+            .map(Threshold::Absolute)
+            .map_err(|e| format!("invalid duration `{s}`: {e}"));
+    };
+
+    match percent.trim().parse() {
+        Ok(percent) if percents.contains(&percent) => Ok(Threshold::Percent(percent)),
+        Ok(_) => Err(format!("percentage must be between {}% and {}%", percents.start(), percents.end())),
+        Err(e) => Err(format!("invalid percentage `{s}`: {e}")),
+    }
+}
@@ -0,0 +1,143 @@
+//! Notifications posted as JSON to one or more HTTP webhooks.
+
+use crate::error::Error;
+use crate::http::{self, Retry};
+use crate::notify::Event;
+use crate::secret::Secret;
+use log::error;
+use std::{sync, thread, time};
+
+/// Environment variable the bearer token is read from, unless `--webhook-token-file` is given.
+pub const TOKEN_ENV_VAR: &str = "WG_RESTARTER_WEBHOOK_TOKEN";
+
+/// What every delivery needs, shared with the threads making them.
+struct Endpoints {
+    urls: Vec<String>,
+    token: Option<Secret>,
+    agent: ureq::Agent,
+    retry: Retry,
+}
+
+/// HTTP endpoints notifications are posted to, with an optional bearer token sent to all of them.
+pub struct Webhook {
+    endpoints: sync::Arc<Endpoints>,
+
+    /// Deliveries still being made, waited for when the webhook is dropped
+    deliveries: sync::Mutex<Vec<thread::JoinHandle<()>>>,
+}
+
+impl Webhook {
+    /// Requests to each of `urls` are given up on after `timeout`, so a slow endpoint cannot stall monitoring,
+    /// and failed ones are retried as per `retry`.
+    pub fn new(urls: Vec<String>, token: Option<Secret>, timeout: time::Duration, retry: Retry) -> Self {
+        let agent = ureq::Agent::config_builder()
+            .timeout_global(Some(timeout))
+            .build()
+            .into();
+
+        Self {
+            endpoints: sync::Arc::new(Endpoints { urls, token, agent, retry }),
+            deliveries: sync::Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Post an event and its rendered message to every endpoint at once, in the background.
+    ///
+    /// Retries happen in a thread of their own, so they never delay monitoring.
+    /// Endpoints that still fail are logged; errors mention the host but neither the rest of the URL nor the token.
+    pub fn send(&self, event: &Event, message: &str) {
+        let body = serde_json::json!({
+            "interface": event.interface,
+            "unit": event.unit,
+            "elapsed_secs": event.elapsed.map(|elapsed| elapsed.as_secs()),
+            "timeout_secs": event.timeout.as_secs(),
+            "outcome": event.outcome,
+            "message": message,
+        });
+
+        let body = body.to_string();
+        let endpoints = sync::Arc::clone(&self.endpoints);
+
+        // Named after the interface, so what it logs is attributed to it
+        let delivery = thread::Builder::new()
+            .name(event.interface.to_string())
+            .spawn(move || {
+                for e in endpoints.deliver(&body) {
+                    error!("{e}");
+                }
+            });
+
+        let mut deliveries = self.deliveries.lock().unwrap_or_else(sync::PoisonError::into_inner);
+        deliveries.retain(|delivery| !delivery.is_finished());
+
+        match delivery {
+            Ok(delivery) => deliveries.push(delivery),
+            Err(e) => error!("failed to spawn webhook thread: {e}"),
+        }
+    }
+}
+
+impl Drop for Webhook {
+    /// Let deliveries still being made finish, so that notifications sent right before exiting are not lost.
+    fn drop(&mut self) {
+        let deliveries = std::mem::take(self.deliveries.get_mut().unwrap_or_else(sync::PoisonError::into_inner));
+
+        for delivery in deliveries {
+            let _ = delivery.join();
+        }
+    }
+}
+
+impl Endpoints {
+    /// Post `body` to every endpoint at once, returning the errors of those that failed.
+    fn deliver(&self, body: &str) -> Vec<Error> {
+        let name = thread::current().name().map(str::to_string);
+
+        // One endpoint being slow or down must not hold up the others
+        thread::scope(|scope| {
+            let requests: Vec<_> = self.urls
+                .iter()
+                .filter_map(|url| {
+                    let mut request = thread::Builder::new();
+
+                    if let Some(name) = &name {
+                        request = request.name(name.clone());
+                    }
+
+                    // As `scope.spawn` would, but keeping the name
+                    match request.spawn_scoped(scope, || self.post(url, body)) {
+                        Ok(request) => Some((url, request)),
+                        Err(e) => {
+                            error!("failed to spawn webhook thread for `{}`: {e}; skipping it ...", http::redact(url));
+                            None
+                        },
+                    }
+                })
+                .collect();
+
+            requests
+                .into_iter()
+                .filter_map(|(url, request)| match request.join() {
+                    Ok(result) => result.err(),
+                    Err(_) => Some(Error::HttpPanicked { url: http::redact(url) }),
+                })
+                .collect()
+        })
+    }
+
+    fn post(&self, url: &str, body: &str) -> Result<(), Error> {
+        self.retry
+            .run(url, || {
+                let mut request = self.agent
+                    .post(url)
+                    .header("Content-Type", "application/json");
+
+                if let Some(token) = &self.token {
+                    request = request.header("Authorization", format!("Bearer {}", token.expose()));
+                }
+
+                request.send(body).map(|_| ())
+            })
+            .map_err(|(source, attempts)| Error::Http { url: http::redact(url), attempts, source })
+    }
+}
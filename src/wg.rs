@@ -0,0 +1,190 @@
+//! Parsing of `wg show` output.
+//!
+//! Output is parsed straight from the raw bytes as returned by the `wg` process,
+//! to avoid a lossy `String` conversion of the whole output on every loop.
+
+/// Parse a decimal integer field, ignoring surrounding whitespace.
+fn parse_u64(field: &[u8]) -> Option<u64> {
+    let digits = field.trim_ascii();
+
+    if digits.is_empty() {
+        return None;
+    }
+
+    digits.iter().try_fold(0u64, |acc, &b| {
+        if !b.is_ascii_digit() {
+            return None;
+        }
+
+        acc.checked_mul(10)?.checked_add(u64::from(b - b'0'))
+    })
+}
+
+/// Split a line into its public key and the remainder after the first tab.
+fn split_key(line: &[u8]) -> Option<(&str, &[u8])> {
+    let tab = line.iter().position(|&b| b == b'\t')?;
+    let public_key = std::str::from_utf8(&line[..tab]).ok()?;
+    Some((public_key, &line[tab + 1..]))
+}
+
+/// Parse every peer's public key and latest-handshake timestamp from `wg show` output.
+pub fn peer_handshakes(output: &[u8]) -> Option<Vec<(&str, u64)>> {
+    // Handshakes are in the tab-separated format "HASH\t1234567890\n"
+    output
+        .split(|&b| b == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (public_key, post) = split_key(line)?;
+            Some((public_key, parse_u64(post)?))
+        })
+        .collect()
+}
+
+/// Parse the first peer's latest-handshake timestamp from `wg show` output.
+pub fn first_peer_handshake_ts(output: &[u8]) -> Option<u64> {
+    let line = output.split(|&b| b == b'\n').next()?;  // first peer only
+    let (_, post) = split_key(line)?;
+    parse_u64(post)
+}
+
+/// Parse every peer's interface, public key and latest-handshake timestamp from
+/// `wg show all latest-handshakes` output, which prefixes each line with the interface.
+pub fn all_peer_handshakes(output: &[u8]) -> Option<Vec<(&str, &str, u64)>> {
+    output
+        .split(|&b| b == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (interface, rest) = split_key(line)?;
+            let (public_key, post) = split_key(rest)?;
+            Some((interface, public_key, parse_u64(post)?))
+        })
+        .collect()
+}
+
+/// Pick the lines of `interface` out of `wg show all` output, without the interface
+/// column, so that they read as the output of `wg show <interface>` would.
+///
+/// An interface without any peers, or that does not exist, has no lines.
+pub fn interface_output(output: &[u8], interface: &str) -> Option<Vec<u8>> {
+    let mut selected = Vec::new();
+
+    for line in output.split(|&b| b == b'\n').filter(|line| !line.is_empty()) {
+        let (line_interface, rest) = split_key(line)?;
+
+        if line_interface == interface {
+            selected.extend_from_slice(rest);
+            selected.push(b'\n');
+        }
+    }
+
+    Some(selected)
+}
+
+/// Column of the latest-handshake timestamp in `wg show <interface> latest-handshakes` output,
+/// counting the public key as column 0.
+pub const HANDSHAKE_COLUMN: usize = 1;
+
+/// Why timestamps could not be read from a given column.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ColumnError {
+    /// A line has too few fields for the column to exist
+    #[error("column {column} is out of range; line {line} has {fields} tab-separated field(s), numbered from 0")]
+    OutOfRange { column: usize, line: usize, fields: usize },
+
+    /// The public key in column 0 is not valid UTF-8
+    #[error("line {line} does not start with a public key")]
+    InvalidKey { line: usize },
+
+    /// The field in the column is not a timestamp
+    #[error("column {column} of line {line} is not a timestamp: `{field}`")]
+    NotATimestamp { column: usize, line: usize, field: String },
+}
+
+/// Parse every peer's public key and the timestamp in `column` from tab-separated output,
+/// where lines are numbered from 1 and columns from 0, the public key being column 0.
+///
+/// For output whose timestamps are not in the usual place, e.g. from patched `wg` versions.
+pub fn peer_handshakes_in_column(output: &[u8], column: usize) -> Result<Vec<(&str, u64)>, ColumnError> {
+    output
+        .split(|&b| b == b'\n')
+        .filter(|line| !line.is_empty())
+        .enumerate()
+        .map(|(i, line)| {
+            let line_number = i + 1;
+            let fields: Vec<&[u8]> = line.split(|&b| b == b'\t').collect();
+
+            let (Some(key), Some(field)) = (fields.first(), fields.get(column)) else {
+                return Err(ColumnError::OutOfRange { column, line: line_number, fields: fields.len() });
+            };
+
+            let public_key = std::str::from_utf8(key)
+                .map_err(|_| ColumnError::InvalidKey { line: line_number })?;
+
+            let timestamp = parse_u64(field).ok_or_else(|| ColumnError::NotATimestamp {
+                column,
+                line: line_number,
+                field: String::from_utf8_lossy(field).into_owned(),
+            })?;
+
+            Ok((public_key, timestamp))
+        })
+        .collect()
+}
+
+/// One peer line of `wg show <interface> dump` output.
+pub struct Peer<'a> {
+    pub public_key: &'a str,
+    pub preshared_key: &'a str,
+    pub endpoint: &'a str,
+    pub allowed_ips: &'a str,
+    pub latest_handshake: u64,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+
+    /// Keepalive interval in seconds, or `None` if "off"
+    pub persistent_keepalive: Option<u64>,
+}
+
+/// Parse one tab-separated peer line of dump output.
+fn parse_dump_peer(line: &[u8]) -> Option<Peer<'_>> {
+    let mut fields = line.split(|&b| b == b'\t');
+    let mut next_str = || std::str::from_utf8(fields.next()?).ok();
+
+    let public_key = next_str()?;
+    let preshared_key = next_str()?;
+    let endpoint = next_str()?;
+    let allowed_ips = next_str()?;
+    let latest_handshake = parse_u64(next_str()?.as_bytes())?;
+    let rx_bytes = parse_u64(next_str()?.as_bytes())?;
+    let tx_bytes = parse_u64(next_str()?.as_bytes())?;
+
+    let persistent_keepalive = match next_str()?.trim() {
+        "off" => None,
+        keepalive => Some(parse_u64(keepalive.as_bytes())?),
+    };
+
+    Some(Peer {
+        public_key,
+        preshared_key,
+        endpoint,
+        allowed_ips,
+        latest_handshake,
+        rx_bytes,
+        tx_bytes,
+        persistent_keepalive,
+    })
+}
+
+/// Parse the peers from `wg show <interface> dump` output.
+///
+/// The first line describes the interface itself and is skipped; every
+/// following line is a peer in the format
+/// "PUBKEY\tPSK\tENDPOINT\tALLOWED_IPS\tHANDSHAKE\tRX\tTX\tKEEPALIVE".
+pub fn parse_dump(output: &[u8]) -> Option<Vec<Peer<'_>>> {
+    let mut lines = output
+        .split(|&b| b == b'\n')
+        .filter(|line| !line.is_empty());
+
+    lines.next()?;  // interface line
+    lines.map(parse_dump_peer).collect()
+}
@@ -0,0 +1,97 @@
+//! Parsing of `wg-quick` configuration files, as far as needed to find peers by name.
+//!
+//! WireGuard itself has no notion of peer names, but configs are commonly
+//! annotated with comments like `# Name = office-gateway`, either inside a
+//! `[Peer]` section, on the lines right above its header, or after a value on
+//! the same line.
+
+/// A `[Peer]` section of a config, with its name comment if it has one.
+#[derive(Debug, PartialEq, Eq)]
+pub struct NamedPeer<'a> {
+    pub name: Option<&'a str>,
+    pub public_key: Option<&'a str>,
+}
+
+/// Parse a `Key = value` pair, as used both for settings and for name comments.
+fn key_value(s: &str) -> Option<(&str, &str)> {
+    let (key, value) = s.split_once('=')?;
+    Some((key.trim(), value.trim()))
+}
+
+/// Parse the value of a `Name = ...` comment, given the text after the `#`.
+fn name_comment(comment: &str) -> Option<&str> {
+    key_value(comment)
+        .filter(|(key, value)| key.eq_ignore_ascii_case("name") && !value.is_empty())
+        .map(|(_, value)| value)
+}
+
+/// Parse every `[Peer]` section of a config.
+pub fn peers(config: &str) -> Vec<NamedPeer<'_>> {
+    let mut peers = Vec::new();
+
+    // The section being read, or `None` outside of a `[Peer]` section
+    let mut current: Option<NamedPeer> = None;
+
+    // A name comment on a line of its own, which names the next `[Peer]` if it
+    // comes right before its header, and otherwise the section it is in
+    let mut pending_name = None;
+
+    for line in config.lines() {
+        let (content, comment) = match line.split_once('#') {
+            Some((content, comment)) => (content.trim(), Some(comment)),
+            None => (line.trim(), None),
+        };
+
+        if content.is_empty() {
+            if let Some(name) = comment.and_then(name_comment) {
+                pending_name = Some(name);
+            }
+
+            continue;
+        }
+
+        if content.starts_with('[') {
+            peers.extend(current.take());
+
+            if content.eq_ignore_ascii_case("[peer]") {
+                current = Some(NamedPeer { name: pending_name.take(), public_key: None });
+            }
+
+            pending_name = None;
+        } else if let Some(peer) = &mut current {
+            if let Some(name) = pending_name.take() {
+                peer.name.get_or_insert(name);
+            }
+
+            if let Some((key, value)) = key_value(content)
+                && key.eq_ignore_ascii_case("publickey")
+            {
+                peer.public_key = Some(value);
+            }
+        } else {
+            pending_name = None;
+        }
+
+        // An inline comment always names the section it is in
+        if let Some(peer) = &mut current
+            && let Some(name) = comment.and_then(name_comment)
+        {
+            peer.name = Some(name);
+        }
+    }
+
+    if let Some(mut peer) = current {
+        peer.name = peer.name.or(pending_name);
+        peers.push(peer);
+    }
+
+    peers
+}
+
+/// Find the public key of the peer named `name`.
+pub fn peer_public_key<'a>(config: &'a str, name: &str) -> Option<&'a str> {
+    peers(config)
+        .into_iter()
+        .find(|peer| peer.name == Some(name))
+        .and_then(|peer| peer.public_key)
+}
@@ -0,0 +1,56 @@
+use std::time::{Duration, UNIX_EPOCH};
+use wg_restarter::clock;
+
+const NOW: u64 = 1_700_000_000;
+
+#[test]
+fn past_timestamp_is_its_age() {
+    let now = UNIX_EPOCH + Duration::from_secs(NOW);
+    assert_eq!(clock::age(now, NOW - 90), Ok(Duration::from_secs(90)));
+}
+
+#[test]
+fn current_timestamp_is_zero() {
+    let now = UNIX_EPOCH + Duration::from_secs(NOW);
+    assert_eq!(clock::age(now, NOW), Ok(Duration::ZERO));
+}
+
+#[test]
+fn future_timestamp_is_how_far_ahead() {
+    let now = UNIX_EPOCH + Duration::from_secs(NOW);
+    assert_eq!(clock::age(now, NOW + 300), Err(Duration::from_secs(300)));
+}
+
+#[test]
+fn systemd_unix_timestamp() {
+    assert_eq!(clock::parse_systemd_timestamp("@1697280000"), Some(UNIX_EPOCH + Duration::from_secs(1_697_280_000)));
+}
+
+#[test]
+fn systemd_unix_timestamp_fraction_is_dropped() {
+    assert_eq!(clock::parse_systemd_timestamp("@1697280000.123456"), Some(UNIX_EPOCH + Duration::from_secs(1_697_280_000)));
+}
+
+#[test]
+fn systemd_utc_timestamp() {
+    // 2026-10-14T12:00:00Z
+    assert_eq!(clock::parse_systemd_timestamp("Tue 2026-10-14 12:00:00 UTC"), Some(UNIX_EPOCH + Duration::from_secs(1_791_979_200)));
+}
+
+#[test]
+fn systemd_local_timestamp_is_ambiguous() {
+    assert_eq!(clock::parse_systemd_timestamp("Tue 2026-10-14 12:00:00 CEST"), None);
+}
+
+#[test]
+fn systemd_empty_timestamp_is_never() {
+    assert_eq!(clock::parse_systemd_timestamp(""), None);
+    assert_eq!(clock::parse_systemd_timestamp("  \n"), None);
+}
+
+#[test]
+fn systemd_malformed_timestamps_are_rejected() {
+    assert_eq!(clock::parse_systemd_timestamp("@"), None);
+    assert_eq!(clock::parse_systemd_timestamp("@abc"), None);
+    assert_eq!(clock::parse_systemd_timestamp("Tue 2026-10-14 12:00:00 UTC extra"), None);
+}
@@ -0,0 +1,65 @@
+use std::time::{Duration, Instant};
+use wg_restarter::cooldown::NotifyCooldown;
+
+const COOLDOWN: Duration = Duration::from_secs(600);
+
+#[test]
+fn first_notification_is_admitted() {
+    let mut cooldown = NotifyCooldown::new();
+    assert!(cooldown.admit("restarted", COOLDOWN, Instant::now()));
+}
+
+#[test]
+fn repeat_within_cooldown_is_suppressed() {
+    let start = Instant::now();
+    let mut cooldown = NotifyCooldown::new();
+
+    assert!(cooldown.admit("restarted", COOLDOWN, start));
+    assert!(!cooldown.admit("restarted", COOLDOWN, start + COOLDOWN - Duration::from_secs(1)));
+}
+
+#[test]
+fn repeat_at_cooldown_end_is_admitted() {
+    let start = Instant::now();
+    let mut cooldown = NotifyCooldown::new();
+
+    assert!(cooldown.admit("restarted", COOLDOWN, start));
+    assert!(cooldown.admit("restarted", COOLDOWN, start + COOLDOWN));
+}
+
+#[test]
+fn event_types_cool_down_apart() {
+    let start = Instant::now();
+    let mut cooldown = NotifyCooldown::new();
+
+    assert!(cooldown.admit("restarted", COOLDOWN, start));
+    assert!(cooldown.admit("breaker opened", COOLDOWN, start));
+}
+
+#[test]
+fn zero_cooldown_suppresses_nothing() {
+    let start = Instant::now();
+    let mut cooldown = NotifyCooldown::new();
+
+    assert!(cooldown.admit("restarted", Duration::ZERO, start));
+    assert!(cooldown.admit("restarted", Duration::ZERO, start));
+}
+
+#[test]
+fn expired_reports_suppressed_counts() {
+    let start = Instant::now();
+    let mut cooldown = NotifyCooldown::new();
+
+    cooldown.admit("restarted", COOLDOWN, start);
+    cooldown.admit("restarted", COOLDOWN, start + Duration::from_secs(1));
+    cooldown.admit("restarted", COOLDOWN, start + Duration::from_secs(2));
+    cooldown.admit("recovered", COOLDOWN, start);
+
+    assert!(cooldown.expired(COOLDOWN, start + Duration::from_secs(3)).is_empty());
+
+    // Only event types that had anything suppressed are reported
+    assert_eq!(cooldown.expired(COOLDOWN, start + COOLDOWN), [("restarted".to_string(), 2)]);
+
+    // And each only once
+    assert!(cooldown.expired(COOLDOWN, start + COOLDOWN).is_empty());
+}
@@ -0,0 +1,22 @@
+use std::time::Duration;
+use wg_restarter::duration;
+
+#[test]
+fn bare_integer_is_seconds() {
+    assert_eq!(duration::parse("600").unwrap(), Duration::from_secs(600));
+}
+
+#[test]
+fn humantime_seconds() {
+    assert_eq!(duration::parse("600s").unwrap(), Duration::from_secs(600));
+}
+
+#[test]
+fn humantime_minutes() {
+    assert_eq!(duration::parse("10m").unwrap(), Duration::from_secs(600));
+}
+
+#[test]
+fn invalid_string_is_rejected() {
+    assert!(duration::parse("ten minutes").is_err());
+}
@@ -0,0 +1,66 @@
+use std::time::{Duration, UNIX_EPOCH};
+use wg_restarter::endpoints::EndpointHistory;
+use wg_restarter::wg::Peer;
+
+const NOW: u64 = 1_700_000_000;
+const WINDOW: Duration = Duration::from_secs(600);
+
+fn peer<'a>(public_key: &'a str, endpoint: &'a str) -> Peer<'a> {
+    Peer {
+        public_key,
+        preshared_key: "(none)",
+        endpoint,
+        allowed_ips: "10.8.0.1/32",
+        latest_handshake: NOW - 3600,
+        rx_bytes: 0,
+        tx_bytes: 0,
+        persistent_keepalive: None,
+    }
+}
+
+#[test]
+fn only_the_moving_peer_behind_a_shared_endpoint_flaps() {
+    let mut history = EndpointHistory::new();
+    let shared = "198.51.100.7:51820";
+
+    // Both start out behind the same endpoint, then only the first keeps moving
+    let endpoints = [shared, "198.51.100.8:51820", shared, "198.51.100.8:51820"];
+    let mut flapping = None;
+
+    for (i, endpoint) in endpoints.iter().enumerate() {
+        let now = UNIX_EPOCH + Duration::from_secs(NOW + 10 * i as u64);
+        let peers = [peer("AAAA", endpoint), peer("BBBB", shared)];
+        flapping = history.update(now, &peers, WINDOW, 2);
+    }
+
+    assert_eq!(flapping, Some(("AAAA", 3)));
+}
+
+#[test]
+fn changes_outside_the_window_are_forgotten() {
+    let mut history = EndpointHistory::new();
+    let endpoints = ["198.51.100.7:51820", "198.51.100.8:51820", "198.51.100.7:51820", "198.51.100.8:51820"];
+    let mut flapping = None;
+
+    for (i, endpoint) in endpoints.iter().enumerate() {
+        let now = UNIX_EPOCH + Duration::from_secs(NOW + 400 * i as u64);
+        flapping = history.update(now, &[peer("AAAA", endpoint)], WINDOW, 2);
+    }
+
+    assert_eq!(flapping, None);
+}
+
+#[test]
+fn handshake_since_first_change_is_not_flapping() {
+    let mut history = EndpointHistory::new();
+    let endpoints = ["198.51.100.7:51820", "198.51.100.8:51820", "198.51.100.7:51820", "198.51.100.8:51820"];
+    let mut flapping = None;
+
+    for (i, endpoint) in endpoints.iter().enumerate() {
+        let now = UNIX_EPOCH + Duration::from_secs(NOW + 10 * i as u64);
+        let peer = Peer { latest_handshake: NOW + 10 * i as u64, ..peer("AAAA", endpoint) };
+        flapping = history.update(now, &[peer], WINDOW, 2);
+    }
+
+    assert_eq!(flapping, None);
+}
@@ -0,0 +1,70 @@
+use std::time::{Duration, Instant};
+use wg_restarter::failures::{Report, RestartFailures};
+
+#[test]
+fn failures_below_the_limit_are_logged() {
+    let start = Instant::now();
+    let mut failures = RestartFailures::new(3);
+
+    assert_eq!(failures.failed("refused".to_string(), start), Report::Failure("refused"));
+    assert_eq!(failures.failed("refused".to_string(), start), Report::Failure("refused"));
+}
+
+#[test]
+fn failure_at_the_limit_goes_quiet() {
+    let start = Instant::now();
+    let mut failures = RestartFailures::new(2);
+
+    failures.failed("refused".to_string(), start);
+    assert_eq!(failures.failed("refused".to_string(), start), Report::GoingQuiet("refused"));
+    assert_eq!(failures.failed("refused".to_string(), start), Report::Quiet);
+}
+
+#[test]
+fn every_limit_further_failures_are_summarized() {
+    let start = Instant::now();
+    let mut failures = RestartFailures::new(2);
+
+    for _ in 0..3 {
+        failures.failed("refused".to_string(), start);
+    }
+
+    let report = failures.failed("refused".to_string(), start + Duration::from_millis(90_500));
+    assert_eq!(report, Report::Summary { message: "refused", count: 4, over: Duration::from_secs(90) });
+}
+
+#[test]
+fn limit_of_one_goes_quiet_at_once() {
+    let start = Instant::now();
+    let mut failures = RestartFailures::new(1);
+
+    assert_eq!(failures.failed("refused".to_string(), start), Report::GoingQuiet("refused"));
+    assert!(matches!(failures.failed("refused".to_string(), start), Report::Summary { count: 2, .. }));
+}
+
+#[test]
+fn different_failure_starts_over() {
+    let start = Instant::now();
+    let mut failures = RestartFailures::new(2);
+
+    failures.failed("refused".to_string(), start);
+    failures.failed("refused".to_string(), start);
+    assert_eq!(failures.failed("timed out".to_string(), start), Report::Failure("timed out"));
+}
+
+#[test]
+fn success_reports_only_quieted_failures() {
+    let start = Instant::now();
+    let mut failures = RestartFailures::new(2);
+
+    failures.failed("refused".to_string(), start);
+    failures.failed("refused".to_string(), start);
+    assert_eq!(failures.succeeded(), None);
+
+    for _ in 0..3 {
+        failures.failed("refused".to_string(), start);
+    }
+
+    assert_eq!(failures.succeeded(), Some(3));
+    assert_eq!(failures.succeeded(), None);
+}
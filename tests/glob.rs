@@ -0,0 +1,30 @@
+use wg_restarter::glob;
+
+#[test]
+fn literal_matches_only_itself() {
+    assert!(glob::matches("wg0", "wg0"));
+    assert!(!glob::matches("wg0", "wg01"));
+}
+
+#[test]
+fn star_matches_any_run() {
+    assert!(glob::matches("test*", "test"));
+    assert!(glob::matches("test*", "test-vpn"));
+    assert!(glob::matches("*-mgmt", "site-a-mgmt"));
+    assert!(glob::matches("wg*x*", "wg-a-x-b"));
+    assert!(!glob::matches("test*", "wg-test"));
+}
+
+#[test]
+fn question_mark_matches_one_character() {
+    assert!(glob::matches("wg?", "wg1"));
+    assert!(!glob::matches("wg?", "wg10"));
+}
+
+#[test]
+fn plain_names_are_not_patterns() {
+    assert!(glob::is_pattern("wg*"));
+    assert!(glob::is_pattern("wg?"));
+    assert!(!glob::is_pattern("wg0"));
+    assert!(!glob::is_pattern("site-a.mgmt"));
+}
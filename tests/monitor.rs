@@ -0,0 +1,199 @@
+//! Runs the monitoring loop over scripted handshake ages with
+//! `--handshake-source replay:PATH`, and asserts on the decisions it makes.
+//!
+//! Each step of a replay is one loop iteration, and the binary exits once
+//! they run out. Ages are measured from the moment each step is read, so the
+//! outcome does not depend on how fast the loop runs. Restarts go through
+//! `--backend script`, so no tunnel, systemd or `wg` is needed. To script a
+//! new scenario, pass [`run`] the ages and whatever flags are under test, and
+//! compare the decisions it returns, which are the labels logged at debug
+//! level as `loop decision: LABEL`.
+
+use std::io::Write;
+use std::{env, fs, path, process, time};
+
+/// A scratch directory of its own for `test`.
+fn scratch(test: &str) -> path::PathBuf {
+    let dir = env::temp_dir().join(format!("wg_restarter-{}-{test}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Monitor over the handshake `ages`, one per iteration, returning the decision
+/// made in each and how many times the restart command was run.
+fn run(test: &str, ages: &[&str], args: &[&str]) -> (Vec<String>, usize) {
+    let dir = scratch(test);
+    let replay = dir.join("replay");
+    let restarts = dir.join("restarts");
+    fs::write(&replay, ages.join("\n")).unwrap();
+    let _ = fs::remove_file(&restarts);
+
+    let output = process::Command::new(env!("CARGO_BIN_EXE_wg_restarter"))
+        .arg("wg0")
+        .arg(format!("--handshake-source=replay:{}", replay.display()))
+        .args(["--backend", "script", "--up-check-command", "true", "--verbose"])
+        .arg(format!("--restart-command=echo >> '{}'", restarts.display()))
+        .args(["--timeout", "10m", "--loop-interval", "0s", "--retry-after-unit-restart", "0s"])
+        .args(args)
+        .env_remove("RUST_LOG")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let decisions = String::from_utf8_lossy(&output.stderr)
+        .lines()
+        .filter_map(|line| line.split_once("loop decision: "))
+        .map(|(_, label)| label.to_string())
+        .collect();
+
+    let restarts = fs::read_to_string(&restarts).map_or(0, |restarts| restarts.lines().count());
+    fs::remove_dir_all(&dir).unwrap();
+    (decisions, restarts)
+}
+
+#[test]
+fn stale_restart_recover() {
+    let (decisions, restarts) = run("stale_restart_recover", &["30", "700", "700", "5", "20"], &[]);
+
+    assert_eq!(decisions, ["healthy", "restarted", "restart_in_flight", "healthy", "healthy"]);
+    assert_eq!(restarts, 1);
+}
+
+#[test]
+fn restart_again_once_in_flight_passed() {
+    let (decisions, restarts) = run("restart_again_once_in_flight_passed", &["700", "700"], &["--restart-in-flight", "0s"]);
+
+    assert_eq!(decisions, ["restarted", "restarted"]);
+    assert_eq!(restarts, 2);
+}
+
+#[test]
+fn breaker_opens_after_unrecovered_restarts() {
+    let (decisions, restarts) = run("breaker_opens_after_unrecovered_restarts", &["700", "700", "700", "5"],
+        &["--restart-in-flight", "0s", "--breaker-after", "2"]);
+
+    assert_eq!(decisions, ["restarted", "restarted", "breaker_open", "healthy"]);
+    assert_eq!(restarts, 2);
+}
+
+#[test]
+fn state_file_carries_breaker_over() {
+    let state = env::temp_dir().join(format!("wg_restarter-{}-state.json", process::id()));
+    let state_file = format!("--state-file={}", state.display());
+    let args = ["--restart-in-flight", "0s", "--breaker-after", "2", &state_file];
+
+    let (decisions, _) = run("state_file_carries_breaker_over", &["700", "700"], &args);
+    assert_eq!(decisions, ["restarted", "restarted"]);
+
+    // A fresh monitor picks up the two restarts without recovery
+    let (decisions, restarts) = run("state_file_carries_breaker_over", &["700", "5", "700"], &args);
+    assert_eq!(decisions, ["breaker_open", "healthy", "restarted"]);
+    assert_eq!(restarts, 1);
+
+    fs::remove_file(&state).unwrap();
+}
+
+#[test]
+fn metrics_textfile_written_on_exit() {
+    let metrics = env::temp_dir().join(format!("wg_restarter-{}-metrics.prom", process::id()));
+    let metrics_textfile = format!("--metrics-textfile={}", metrics.display());
+
+    run("metrics_textfile_written_on_exit", &["700", "5"], &[&metrics_textfile]);
+    let text = fs::read_to_string(&metrics).unwrap();
+
+    assert!(text.contains("wg_restarter_restarts_total{interface=\"wg0\",unit=\"wg-quick@wg0.service\"} 1\n"), "{text}");
+    assert!(text.contains("wg_restarter_recoveries_total{interface=\"wg0\",unit=\"wg-quick@wg0.service\"} 1\n"), "{text}");
+    assert!(text.contains("decision=\"exit\"} 1\n"), "{text}");
+    assert!(!metrics.with_extension("prom.tmp").exists());
+
+    fs::remove_file(&metrics).unwrap();
+}
+
+#[test]
+fn overshoot_within_deadband_does_not_restart() {
+    let (decisions, restarts) = run("overshoot_within_deadband_does_not_restart", &["605", "615"], &["--overshoot-deadband", "10s"]);
+
+    assert_eq!(decisions, ["in_deadband", "restarted"]);
+    assert_eq!(restarts, 1);
+}
+
+#[test]
+fn restart_effectiveness_counted() {
+    let metrics = env::temp_dir().join(format!("wg_restarter-{}-effectiveness.prom", process::id()));
+    let metrics_textfile = format!("--metrics-textfile={}", metrics.display());
+
+    // The first restart is followed by another, the second by a fresh handshake
+    let (_, restarts) = run("restart_effectiveness_counted", &["700", "700", "5"], &["--restart-in-flight", "0s", &metrics_textfile]);
+    let text = fs::read_to_string(&metrics).unwrap();
+
+    assert_eq!(restarts, 2);
+    assert!(text.contains("wg_restarter_effective_restarts_total{interface=\"wg0\",unit=\"wg-quick@wg0.service\"} 1\n"), "{text}");
+    assert!(text.contains("wg_restarter_ineffective_restarts_total{interface=\"wg0\",unit=\"wg-quick@wg0.service\"} 1\n"), "{text}");
+
+    fs::remove_file(&metrics).unwrap();
+}
+
+#[test]
+fn failed_up_check_restarts() {
+    let count = env::temp_dir().join(format!("wg_restarter-{}-up-check", process::id()));
+    let _ = fs::remove_file(&count);
+
+    // Up at startup, down on the first check, up again after the restart
+    let up_check_command = format!("--up-check-command=n=$(cat '{0}' 2>/dev/null || echo 0); echo $((n + 1)) > '{0}'; [ \"$n\" != 1 ]", count.display());
+    let (decisions, restarts) = run("failed_up_check_restarts", &["5", "5", "5"], &[&up_check_command]);
+
+    assert_eq!(decisions, ["restarted", "healthy", "healthy"]);
+    assert_eq!(restarts, 1);
+
+    fs::remove_file(&count).unwrap();
+}
+
+#[test]
+fn future_handshake_warns() {
+    let (decisions, restarts) = run("future_handshake_warns", &["5", "-600", "5"], &["--on-clock-anomaly", "warn"]);
+
+    assert_eq!(decisions, ["healthy", "healthy", "healthy"]);
+    assert_eq!(restarts, 0);
+}
+
+#[test]
+fn future_handshake_restarts() {
+    let (decisions, restarts) = run("future_handshake_restarts", &["5", "-600", "5"], &["--on-clock-anomaly", "restart"]);
+
+    assert_eq!(decisions, ["healthy", "restarted", "healthy"]);
+    assert_eq!(restarts, 1);
+}
+
+#[test]
+fn future_handshake_skipped() {
+    // Far enough ahead of any clock this runs on
+    let (decisions, restarts) = run("future_handshake_skipped", &["5", "@4102444800", "5"], &["--on-clock-anomaly", "skip"]);
+
+    assert_eq!(decisions, ["healthy", "clock_anomaly", "healthy"]);
+    assert_eq!(restarts, 0);
+}
+
+#[test]
+fn stdin_driver_shares_deadband() {
+    let now = time::SystemTime::now().duration_since(time::UNIX_EPOCH).unwrap().as_secs();
+    let decide = |args: &[&str]| {
+        let mut child = process::Command::new(env!("CARGO_BIN_EXE_wg_restarter"))
+            .args(["--stdin-driver", "--timeout", "10m"])
+            .args(args)
+            .stdin(process::Stdio::piped())
+            .stdout(process::Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let mut stdin = child.stdin.take().unwrap();
+        writeln!(stdin, "wg0\t{}", now - 620).unwrap();
+        drop(stdin);
+
+        String::from_utf8(child.wait_with_output().unwrap().stdout).unwrap()
+    };
+
+    // 20s past the timeout
+    assert_eq!(decide(&[]), "restart wg0\n");
+    assert_eq!(decide(&["--overshoot-deadband", "30s"]), "ok wg0\n");
+}
@@ -0,0 +1,75 @@
+use std::time::{Duration, Instant};
+use wg_restarter::rate_limit::{self, RateLimit, RestartHistory};
+
+const HOUR: Duration = Duration::from_secs(3600);
+
+#[test]
+fn count_and_window() {
+    assert_eq!(rate_limit::parse("5/1h"), Ok(RateLimit { count: 5, window: HOUR }));
+}
+
+#[test]
+fn window_in_bare_seconds() {
+    assert_eq!(rate_limit::parse("3/600"), Ok(RateLimit { count: 3, window: Duration::from_secs(600) }));
+}
+
+#[test]
+fn zero_count_is_rejected() {
+    assert!(rate_limit::parse("0/1h").is_err());
+}
+
+#[test]
+fn malformed_limits_are_rejected() {
+    assert!(rate_limit::parse("5").is_err());
+    assert!(rate_limit::parse("/1h").is_err());
+    assert!(rate_limit::parse("five/1h").is_err());
+    assert!(rate_limit::parse("5/").is_err());
+    assert!(rate_limit::parse("5/soon").is_err());
+    assert!(rate_limit::parse("-1/1h").is_err());
+}
+
+#[test]
+fn restarts_up_to_the_count_are_allowed() {
+    let limit = RateLimit { count: 2, window: HOUR };
+    let start = Instant::now();
+    let mut history = RestartHistory::new();
+
+    assert!(history.allows(&limit, start));
+    history.record(start);
+    assert!(history.allows(&limit, start));
+    history.record(start);
+
+    // The count itself is reached, so no more
+    assert!(!history.allows(&limit, start + Duration::from_secs(1)));
+}
+
+#[test]
+fn restart_at_the_window_edge_still_counts() {
+    let limit = RateLimit { count: 1, window: HOUR };
+    let start = Instant::now();
+    let mut history = RestartHistory::new();
+
+    history.record(start);
+    assert!(!history.allows(&limit, start + HOUR));
+}
+
+#[test]
+fn restarts_past_the_window_are_forgotten() {
+    let limit = RateLimit { count: 1, window: HOUR };
+    let start = Instant::now();
+    let mut history = RestartHistory::new();
+
+    history.record(start);
+    assert!(history.allows(&limit, start + HOUR + Duration::from_secs(1)));
+}
+
+#[test]
+fn zero_window_forgets_at_once() {
+    let limit = RateLimit { count: 1, window: Duration::ZERO };
+    let start = Instant::now();
+    let mut history = RestartHistory::new();
+
+    history.record(start);
+    assert!(!history.allows(&limit, start));
+    assert!(history.allows(&limit, start + Duration::from_millis(1)));
+}
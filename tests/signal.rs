@@ -0,0 +1,26 @@
+use wg_restarter::signal::{self, Signal};
+
+#[test]
+fn bare_name() {
+    assert_eq!(signal::parse("HUP"), Ok(Signal { name: "HUP", number: 1 }));
+}
+
+#[test]
+fn sig_prefix_and_case_are_ignored() {
+    assert_eq!(signal::parse("SIGUSR1"), Ok(Signal { name: "USR1", number: 10 }));
+    assert_eq!(signal::parse(" sigterm "), Ok(Signal { name: "TERM", number: 15 }));
+}
+
+#[test]
+fn displayed_with_prefix() {
+    assert_eq!(signal::parse("winch").unwrap().to_string(), "SIGWINCH");
+}
+
+#[test]
+fn unknown_and_malformed_signals_are_rejected() {
+    assert!(signal::parse("").is_err());
+    assert!(signal::parse("SIG").is_err());
+    assert!(signal::parse("SIGSIGHUP").is_err());
+    assert!(signal::parse("1").is_err());
+    assert!(signal::parse("RTMIN").is_err());
+}
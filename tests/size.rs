@@ -0,0 +1,24 @@
+use wg_restarter::size;
+
+#[test]
+fn bare_integer_is_bytes() {
+    assert_eq!(size::parse("1048576").unwrap(), 1_048_576);
+}
+
+#[test]
+fn suffixes_are_binary() {
+    assert_eq!(size::parse("512K").unwrap(), 512 * 1024);
+    assert_eq!(size::parse("10M").unwrap(), 10 * 1024 * 1024);
+    assert_eq!(size::parse("1g").unwrap(), 1024 * 1024 * 1024);
+}
+
+#[test]
+fn invalid_string_is_rejected() {
+    assert!(size::parse("ten megabytes").is_err());
+    assert!(size::parse("M").is_err());
+}
+
+#[test]
+fn overflow_is_rejected() {
+    assert!(size::parse("18446744073709551615G").is_err());
+}
@@ -0,0 +1,41 @@
+use std::time::Duration;
+use wg_restarter::threshold::{self, Threshold};
+
+const TIMEOUT: Duration = Duration::from_secs(600);
+
+#[test]
+fn duration_is_absolute() {
+    assert_eq!(threshold::parse("5m", 1..=99), Ok(Threshold::Absolute(Duration::from_secs(300))));
+    assert_eq!(threshold::parse("300", 1..=99), Ok(Threshold::Absolute(Duration::from_secs(300))));
+}
+
+#[test]
+fn percentage_of_timeout() {
+    let threshold = threshold::parse("70%", 1..=99).unwrap();
+
+    assert_eq!(threshold, Threshold::Percent(70));
+    assert_eq!(threshold.resolve(TIMEOUT), Duration::from_secs(420));
+}
+
+#[test]
+fn percentages_at_the_bounds_are_accepted() {
+    assert_eq!(threshold::parse("1%", 1..=99), Ok(Threshold::Percent(1)));
+    assert_eq!(threshold::parse("99%", 1..=99), Ok(Threshold::Percent(99)));
+    assert_eq!(threshold::parse("100%", 1..=100).unwrap().resolve(TIMEOUT), TIMEOUT);
+}
+
+#[test]
+fn percentages_past_the_bounds_are_rejected() {
+    assert!(threshold::parse("0%", 1..=99).is_err());
+    assert!(threshold::parse("100%", 1..=99).is_err());
+    assert!(threshold::parse("101%", 1..=100).is_err());
+}
+
+#[test]
+fn malformed_thresholds_are_rejected() {
+    assert!(threshold::parse("", 1..=99).is_err());
+    assert!(threshold::parse("%", 1..=99).is_err());
+    assert!(threshold::parse("-5%", 1..=99).is_err());
+    assert!(threshold::parse("half%", 1..=99).is_err());
+    assert!(threshold::parse("soon", 1..=99).is_err());
+}
@@ -0,0 +1,143 @@
+//! Runs the parsers over the captures of `wg` output in `tests/fixtures/`.
+//!
+//! The output format of `wg show` has not changed between wireguard-tools
+//! releases, so the fixtures vary in shape rather than version: one peer or
+//! several, peers that never handshaked, peers behind the same endpoint, and
+//! interfaces without peers.
+
+use std::{fs, path::Path};
+use wg_restarter::wg;
+
+/// A `latest-handshakes` capture and the timestamps it should parse into, in order.
+const LATEST_HANDSHAKES: &[(&str, &[u64])] = &[
+    ("single-peer.txt", &[1760435000]),
+    ("multi-peer.txt", &[1760435000, 1760434880, 1760431200]),
+    ("never-handshaked.txt", &[0]),
+    ("partly-handshaked.txt", &[0, 1760434880]),
+    ("no-peers.txt", &[]),
+];
+
+/// A `wg show all latest-handshakes` capture and the interface and timestamp of each peer, in order.
+const ALL_LATEST_HANDSHAKES: &[(&str, &[(&str, u64)])] = &[
+    ("multi-interface.txt", &[("wg0", 1760435000), ("wg0", 1760434880), ("site-b", 0), ("wg1", 1760431200)]),
+    ("no-peers.txt", &[]),
+];
+
+/// A `dump` capture and the handshake timestamp and received bytes of each peer, in order.
+const DUMPS: &[(&str, &[(u64, u64)])] = &[
+    ("single-peer.txt", &[(1760435000, 15288)]),
+    ("multi-peer.txt", &[(1760435000, 1048576), (1760434880, 4096), (1760431200, 0)]),
+    ("never-handshaked.txt", &[(0, 0)]),
+    ("shared-endpoint.txt", &[(1760435000, 15288), (1760431200, 0)]),
+    ("no-peers.txt", &[]),
+];
+
+fn fixture(kind: &str, name: &str) -> Vec<u8> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(kind).join(name);
+    fs::read(&path).unwrap_or_else(|e| panic!("failed to read {}: {e}", path.display()))
+}
+
+#[test]
+fn latest_handshakes_fixtures_parse() {
+    for &(name, expected) in LATEST_HANDSHAKES {
+        let output = fixture("latest-handshakes", name);
+        let peers = wg::peer_handshakes(&output).unwrap_or_else(|| panic!("{name} did not parse"));
+        let timestamps: Vec<u64> = peers.iter().map(|&(_, ts)| ts).collect();
+
+        assert_eq!(timestamps, expected, "{name}");
+        assert!(peers.iter().all(|(key, _)| key.len() == 44), "{name} has a malformed key");
+    }
+}
+
+#[test]
+fn latest_handshakes_fixtures_first_peer() {
+    for &(name, expected) in LATEST_HANDSHAKES {
+        let output = fixture("latest-handshakes", name);
+        assert_eq!(wg::first_peer_handshake_ts(&output), expected.first().copied(), "{name}");
+    }
+}
+
+#[test]
+fn latest_handshakes_fixtures_default_column() {
+    for &(name, _) in LATEST_HANDSHAKES {
+        let output = fixture("latest-handshakes", name);
+
+        assert_eq!(
+            wg::peer_handshakes_in_column(&output, wg::HANDSHAKE_COLUMN).ok(),
+            wg::peer_handshakes(&output),
+            "{name}");
+    }
+}
+
+#[test]
+fn dump_fixtures_parse() {
+    for &(name, expected) in DUMPS {
+        let output = fixture("dump", name);
+        let peers = wg::parse_dump(&output).unwrap_or_else(|| panic!("{name} did not parse"));
+        let readings: Vec<(u64, u64)> = peers.iter().map(|peer| (peer.latest_handshake, peer.rx_bytes)).collect();
+
+        assert_eq!(readings, expected, "{name}");
+    }
+}
+
+#[test]
+fn dump_fixture_fields() {
+    let output = fixture("dump", "multi-peer.txt");
+    let peers = wg::parse_dump(&output).unwrap();
+
+    assert_eq!(peers[0].allowed_ips, "10.8.0.1/32,fd00:8::1/128");
+    assert_ne!(peers[0].preshared_key, "(none)");
+    assert_eq!(peers[0].persistent_keepalive, Some(25));
+    assert_eq!(peers[1].endpoint, "[2001:db8::7]:51820");
+    assert_eq!(peers[1].persistent_keepalive, None);
+}
+
+#[test]
+fn dump_fixture_never_handshaked_has_no_endpoint() {
+    let output = fixture("dump", "never-handshaked.txt");
+    let peers = wg::parse_dump(&output).unwrap();
+
+    assert_eq!(peers[0].endpoint, "(none)");
+    assert_eq!(peers[0].tx_bytes, 0);
+}
+
+#[test]
+fn all_latest_handshakes_fixtures_parse() {
+    for &(name, expected) in ALL_LATEST_HANDSHAKES {
+        let output = fixture("latest-handshakes-all", name);
+        let handshakes = wg::all_peer_handshakes(&output).unwrap_or_else(|| panic!("{name} did not parse"));
+        let readings: Vec<(&str, u64)> = handshakes.iter().map(|&(interface, _, ts)| (interface, ts)).collect();
+
+        assert_eq!(readings, expected, "{name}");
+    }
+}
+
+#[test]
+fn all_latest_handshakes_select_interface() {
+    let output = fixture("latest-handshakes-all", "multi-interface.txt");
+
+    let wg0 = wg::interface_output(&output, "wg0").unwrap();
+    let handshakes = wg::peer_handshakes(&wg0).unwrap();
+    assert_eq!(handshakes.iter().map(|&(_, ts)| ts).collect::<Vec<_>>(), [1760435000, 1760434880]);
+    assert_eq!(handshakes[0].0, "vblMc9U8RAI6cVopcKEMTVT6lVC3D9nTTMSwot5d3L4=");
+
+    assert_eq!(wg::first_peer_handshake_ts(&wg::interface_output(&output, "site-b").unwrap()), Some(0));
+    assert!(wg::interface_output(&output, "wg2").unwrap().is_empty());
+}
+
+#[test]
+fn all_latest_handshakes_rejects_per_interface_output() {
+    let output = fixture("latest-handshakes", "single-peer.txt");
+    assert!(wg::all_peer_handshakes(&output).is_none());
+}
+
+#[test]
+fn dump_fixture_shared_endpoint_keeps_peers_apart() {
+    let output = fixture("dump", "shared-endpoint.txt");
+    let peers = wg::parse_dump(&output).unwrap();
+
+    assert_eq!(peers[0].endpoint, peers[1].endpoint);
+    assert_ne!(peers[0].public_key, peers[1].public_key);
+    assert_eq!(peers[0].allowed_ips, "10.8.0.1/32");
+    assert_eq!(peers[1].allowed_ips, "10.8.0.2/32");
+}
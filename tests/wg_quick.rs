@@ -0,0 +1,51 @@
+use wg_restarter::wg_quick;
+
+const CONFIG: &str = "\
+[Interface]
+# Name = not-a-peer
+PrivateKey = cHJpdmF0ZWtleXByaXZhdGVrZXlwcml2YXRla2V5cHI=
+Address = 10.8.0.1/24
+
+[Peer]
+# Name = office-gateway
+PublicKey = vblMc9U8RAI6cVopcKEMTVT6lVC3D9nTTMSwot5d3L4=
+AllowedIPs = 10.8.0.2/32
+
+# Name = laptop
+[Peer]
+PublicKey = AvldyrZ12Pf90jzf3AXmhPwg3UcI+jtJHfbpBlupvko=
+AllowedIPs = 10.8.0.3/32
+
+[Peer]  # Name = phone
+PublicKey = 4deEImv8zGvsyBmmfC2G0eQkbyMzyGuz/YK7pcYETwM=
+
+[Peer]
+PublicKey = bm9uYW1lbm9uYW1lbm9uYW1lbm9uYW1lbm9uYW1lbm8=
+";
+
+#[test]
+fn name_comment_inside_section() {
+    assert_eq!(wg_quick::peer_public_key(CONFIG, "office-gateway"), Some("vblMc9U8RAI6cVopcKEMTVT6lVC3D9nTTMSwot5d3L4="));
+}
+
+#[test]
+fn name_comment_above_header() {
+    assert_eq!(wg_quick::peer_public_key(CONFIG, "laptop"), Some("AvldyrZ12Pf90jzf3AXmhPwg3UcI+jtJHfbpBlupvko="));
+}
+
+#[test]
+fn inline_name_comment() {
+    assert_eq!(wg_quick::peer_public_key(CONFIG, "phone"), Some("4deEImv8zGvsyBmmfC2G0eQkbyMzyGuz/YK7pcYETwM="));
+}
+
+#[test]
+fn interface_section_is_not_a_peer() {
+    assert_eq!(wg_quick::peer_public_key(CONFIG, "not-a-peer"), None);
+}
+
+#[test]
+fn unnamed_peers_are_listed() {
+    let peers = wg_quick::peers(CONFIG);
+    assert_eq!(peers.len(), 4);
+    assert_eq!(peers[3].name, None);
+}